@@ -0,0 +1,20 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use prust::utils::ice_candidate::{CandidatePolicy, IceCandidate};
+
+fuzz_target!(|data: &[u8]| {
+    let line = match std::str::from_utf8(data) {
+        Ok(line) => line,
+        Err(_) => return,
+    };
+    for policy in CandidatePolicy::ALL.iter() {
+        let _ = policy.apply_line(line);
+    }
+    if let Ok(candidate) = line.parse::<IceCandidate>() {
+        let parsed = candidate
+            .to_string()
+            .parse::<IceCandidate>()
+            .expect("A serialized candidate parses");
+        assert_eq!(parsed, candidate);
+    }
+});
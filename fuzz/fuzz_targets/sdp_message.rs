@@ -0,0 +1,33 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use prust::utils::ice_candidate::CandidatePolicy;
+use prust::utils::sdp::SessionDescription;
+use prust::utils::socket::SDPMessage;
+
+// The descriptions and the conversions applied to them before they reach the browser
+fuzz_target!(|data: &[u8]| {
+    if let Ok(sdp_message) = serde_json::from_slice::<SDPMessage>(data) {
+        let _ = sdp_message.parse();
+    }
+
+    let sdp = match std::str::from_utf8(data) {
+        Ok(sdp) => sdp,
+        Err(_) => return,
+    };
+    for policy in CandidatePolicy::ALL.iter() {
+        let _ = policy.filter_sdp(sdp);
+    }
+    if let Ok(description) = sdp.parse::<SessionDescription>() {
+        let _ = description.validate_webrtc();
+        let _ = description.summary();
+        for media in &description.media {
+            let _ = media.candidates();
+            let _ = media.codecs();
+            let _ = media.sctp_port();
+        }
+        // A parsed description serializes back to an equivalent one
+        let serialized = description.to_string();
+        let parsed = serialized.parse::<SessionDescription>().expect("A serialized description parses");
+        assert_eq!(parsed.to_string(), serialized);
+    }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use prust::utils::session_code;
+
+// Pasted or scanned by the user, straight from the other peer
+fuzz_target!(|data: &[u8]| {
+    let code = match std::str::from_utf8(data) {
+        Ok(code) => code,
+        Err(_) => return,
+    };
+    if let Ok(sdp_message) = session_code::decode(code) {
+        let encoded = session_code::encode(&sdp_message).expect("A decoded description encodes");
+        assert_eq!(session_code::decode(&encoded).unwrap(), sdp_message);
+    }
+});
@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use prust::utils::socket::SignalingMessage;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = serde_json::from_slice::<SignalingMessage>(data) {
+        let serialized = serde_json::to_vec(&message).expect("A parsed message serializes");
+        let parsed = serde_json::from_slice::<SignalingMessage>(&serialized).expect("A serialized message parses");
+        assert_eq!(parsed, message);
+    }
+});
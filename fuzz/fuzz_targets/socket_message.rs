@@ -0,0 +1,31 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use prust::negotiation::{Event, Negotiation};
+use prust::utils::socket::{SignalingMessage, SocketMessage};
+
+// What the signaling server sends, up to the negotiation it drives
+fuzz_target!(|data: &[u8]| {
+    let json = match std::str::from_utf8(data) {
+        Ok(json) => json,
+        Err(_) => return,
+    };
+    let message = match json.parse::<SocketMessage>() {
+        Ok(message) => message,
+        Err(_) => return,
+    };
+    let serialized = serde_json::to_string(&message).expect("A parsed message serializes");
+    serialized.parse::<SocketMessage>().expect("A serialized message parses");
+
+    let mut negotiation = Negotiation::new(true);
+    let event = match message {
+        SocketMessage::JoinedRoom { content } => Event::JoinedRoom(content.room),
+        SocketMessage::SignalMessageToClient { content } => match content {
+            SignalingMessage::UserHere { message } => Event::PeerHere(message),
+            SignalingMessage::ICECandidate { message } => Event::RemoteCandidate(message),
+            SignalingMessage::SDP { message, order } => Event::RemoteDescription(message, order),
+            SignalingMessage::UserLeft => Event::PeerLeft,
+        },
+        _ => return,
+    };
+    negotiation.handle(event);
+});
@@ -4,9 +4,11 @@ use serde::{Deserialize, Serialize};
 use yew::prelude::*;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum SenderType {
     ME,
     YOU,
+    SYSTEM,
 }
 
 impl Display for SenderType {
@@ -14,6 +16,7 @@ impl Display for SenderType {
         match self {
             SenderType::ME => write!(f, "me"),
             SenderType::YOU => write!(f, "u"),
+            SenderType::SYSTEM => write!(f, "system"),
         }
     }
 }
@@ -1,21 +1,33 @@
-use web_sys::HtmlInputElement;
+use web_sys::HtmlTextAreaElement;
 use web_sys::KeyboardEvent;
 use yew::agent::{Dispatched, Dispatcher};
 use yew::prelude::*;
 
-use crate::components::chat_message::{ChatMessage, SenderType};
-use crate::event_bus::{EventBus, Request};
+use crate::peer_agent::{ConversationId, PeerAgent, PeerRequest};
+use crate::utils::settings::SendKey;
 
 pub struct ChatBox {
     link: ComponentLink<Self>,
     node_ref: NodeRef,
-    event_bus: Dispatcher<EventBus>,
+    peer: Dispatcher<PeerAgent>,
+    props: ChatBoxProps,
+}
+
+#[derive(Properties, Clone)]
+pub struct ChatBoxProps {
+    pub conversation: ConversationId,
+    /// Typed earlier in the conversation
+    #[prop_or_default]
+    pub draft: String,
+    /// Receives the text left in the chat box when another conversation is shown
+    pub on_draft: Callback<(ConversationId, String)>,
+    pub send_key: SendKey,
 }
 
 impl ChatBox {
     fn send_message(&mut self) {
-        if let Some(input) = self.node_ref.cast::<HtmlInputElement>() {
-            self.event_bus.send(Request::EventBusMsg(ChatMessage::new(SenderType::ME, input.value())));
+        if let Some(input) = self.node_ref.cast::<HtmlTextAreaElement>() {
+            self.peer.send(PeerRequest::Send(self.props.conversation, input.value()));
             input.set_value("");
         }
     }
@@ -28,13 +40,14 @@ pub enum Msg {
 
 impl Component for ChatBox {
     type Message = Msg;
-    type Properties = ();
+    type Properties = ChatBoxProps;
 
-    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         Self {
             link,
             node_ref: NodeRef::default(),
-            event_bus: EventBus::dispatcher(),
+            peer: PeerAgent::dispatcher(),
+            props,
         }
     }
 
@@ -43,7 +56,11 @@ impl Component for ChatBox {
             Msg::SendMessage => self.send_message(),
 
             Msg::ReturnCarriage(e) => {
-                if e.key_code() == 13 && !e.ctrl_key() && !e.shift_key() {
+                let send = match self.props.send_key {
+                    SendKey::Enter => !e.ctrl_key() && !e.shift_key(),
+                    SendKey::CtrlEnter => e.ctrl_key() || e.meta_key(),
+                };
+                if e.key_code() == 13 && send {
                     self.send_message();
                     e.prevent_default();
                 }
@@ -52,13 +69,26 @@ impl Component for ChatBox {
         true
     }
 
-    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
-        // Should only return "true" if new properties are different to
-        // previously received properties.
-        // This component has no properties so we will always return "false".
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        // The textarea is kept, only its text changes with the conversation
+        if props.conversation != self.props.conversation {
+            if let Some(input) = self.node_ref.cast::<HtmlTextAreaElement>() {
+                self.props.on_draft.emit((self.props.conversation, input.value()));
+                input.set_value(&props.draft);
+            }
+        }
+        self.props = props;
         false
     }
 
+    fn rendered(&mut self, first_render: bool) {
+        if first_render {
+            if let Some(input) = self.node_ref.cast::<HtmlTextAreaElement>() {
+                input.set_value(&self.props.draft);
+            }
+        }
+    }
+
     fn view(&self) -> Html {
         html! {
             <>
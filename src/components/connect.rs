@@ -1,24 +1,342 @@
-use web_sys::HtmlInputElement;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
 use yew::prelude::*;
 use yew::Callback;
 
+use crate::components::qr_code::QrCode;
+use crate::components::qr_scanner::QrScanner;
+use crate::peer_agent::ConversationId;
+use crate::utils::clipboard::copy_to_clipboard;
+use crate::utils::ice_candidate::CandidatePolicy;
 use crate::utils::participants::Participants;
+use crate::utils::room_code::RoomCode;
+use crate::utils::route::invite_link;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ConnectMode {
+    Server(Participants),
+    // Shared room of an invite link, the host created it
+    Room { participants: Participants, host: bool },
+    // Manual signaling, the users exchange session codes themselves
+    CreateInvitation,
+    AcceptInvitation(String),
+    CompleteInvitation(String),
+    // Echo bot in the same page
+    Demo,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConnectRequest {
+    pub mode: ConnectMode,
+    pub candidate_policy: CandidatePolicy,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Signaling {
+    Server,
+    Manual,
+    Demo,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ManualRole {
+    Inviting,
+    Answering,
+}
 
 pub struct Connect {
     link: ComponentLink<Self>,
     my_name: NodeRef,
     other_name: NodeRef,
-    display_connect: bool,
+    candidate_policy: NodeRef,
+    pasted_code: NodeRef,
+    room_code: NodeRef,
+    room_code_error: Option<String>,
+    signaling: Signaling,
+    // The manual sessions of the other conversations go on in the background
+    manual_roles: HashMap<ConversationId, ManualRole>,
+    scanning: bool,
     props: ConnectProps,
 }
 
 #[derive(Properties, Clone)]
 pub struct ConnectProps {
-    pub on_connect: Callback<Participants>,
+    pub on_connect: Callback<ConnectRequest>,
+    pub on_disconnect: Callback<()>,
+    /// Shown conversation, the form connects it
+    pub conversation: ConversationId,
+    pub connected: bool,
+    /// Code to hand to the other user in manual mode, once it is ready
+    #[prop_or_default]
+    pub session_code: Option<String>,
+    /// Room of the invite link the page was opened with
+    #[prop_or_default]
+    pub room: Option<String>,
+    /// Shared room of the current conversation
+    #[prop_or_default]
+    pub joined_room: Option<String>,
+    /// Fills the name fields, from the settings
+    #[prop_or_default]
+    pub display_name: String,
 }
 
 pub enum Msg {
+    ChangeSignaling(ChangeData),
     ClickConnect(MouseEvent),
+    ClickCreateRoom(MouseEvent),
+    ClickJoinRoom(MouseEvent),
+    ClickJoinRoomCode(MouseEvent),
+    ClickCopyInviteLink(MouseEvent),
+    ClickCreateInvitation(MouseEvent),
+    ClickAcceptInvitation(MouseEvent),
+    ClickCompleteInvitation(MouseEvent),
+    ClickCopySessionCode(MouseEvent),
+    ClickStartDemo(MouseEvent),
+    ClickScan(MouseEvent),
+    Scanned(String),
+    ClickDisconnect(MouseEvent),
+}
+
+impl Connect {
+    fn manual_role(&self) -> Option<ManualRole> {
+        self.manual_roles.get(&self.props.conversation).copied()
+    }
+
+    fn set_manual_role(&mut self, role: ManualRole) {
+        self.manual_roles.insert(self.props.conversation, role);
+    }
+
+    fn candidate_policy(&self) -> CandidatePolicy {
+        self.candidate_policy
+            .cast::<HtmlSelectElement>()
+            .and_then(|select| CandidatePolicy::from_value(&select.value()))
+            .unwrap_or_default()
+    }
+
+    fn pasted_code(&self) -> Option<String> {
+        self.pasted_code
+            .cast::<HtmlTextAreaElement>()
+            .map(|textarea| textarea.value())
+            .filter(|code| !code.trim().is_empty())
+    }
+
+    fn my_name(&self) -> Option<String> {
+        self.my_name
+            .cast::<HtmlInputElement>()
+            .map(|input| input.value().trim().to_string())
+            .filter(|name| !name.is_empty())
+    }
+
+    fn join_room(&self, room: String, host: bool) {
+        match self.my_name() {
+            Some(name) => self.emit(ConnectMode::Room {
+                participants: Participants {
+                    user_from: name,
+                    user_to: String::new(),
+                    room: Some(room),
+                },
+                host,
+            }),
+            None => log::error!("The name is mandatory"),
+        }
+    }
+
+    fn emit(&self, mode: ConnectMode) {
+        // Emit this in lib.rs
+        self.props.on_connect.emit(ConnectRequest {
+            mode,
+            candidate_policy: self.candidate_policy(),
+        });
+    }
+
+    fn view_scanner(&self) -> Html {
+        html! {
+            <>
+                <button class="connect__scan" onclick=self.link.callback(|e: MouseEvent| Msg::ClickScan(e))>
+                    { if self.scanning { "Stop scanning" } else { "Scan a QR code" } }
+                </button><br/>
+                {
+                    if self.scanning {
+                        html! { <QrScanner on_scan=self.link.callback(Msg::Scanned)/> }
+                    } else {
+                        html! {}
+                    }
+                }
+            </>
+        }
+    }
+
+    fn view_candidate_policy(&self) -> Html {
+        html! {
+            <>
+                <label for="candidate-policy">{"Privacy"}</label><br/>
+                <select id="candidate-policy" ref=self.candidate_policy.clone()>
+                    { for CandidatePolicy::ALL.iter().map(|policy| html! {
+                        <option value=policy.value()>{ policy.label() }</option>
+                    }) }
+                </select><br/><br/>
+            </>
+        }
+    }
+
+    fn view_server_form(&self) -> Html {
+        html! {
+            <>
+                <label for="name">{"Your Name"}</label><br/>
+                <input id="name" ref=self.my_name.clone() type="text" value=self.props.display_name.clone()/><br/><br/>
+
+                <label for="recipient">{"Your friend's name"}</label><br/>
+                <input id="recipient" ref=self.other_name.clone() type="text"/><br/><br/>
+
+                { self.view_candidate_policy() }
+
+                <button id="connect" onclick=self.link.callback(|e: MouseEvent| Msg::ClickConnect(e))>
+                    {"Connect"}
+                </button><br/><br/>
+
+                <p>{"Or create a room and send its link or its code to your friend, only your name is needed."}</p>
+                <button id="create-room" onclick=self.link.callback(|e: MouseEvent| Msg::ClickCreateRoom(e))>
+                    {"Create a room"}
+                </button><br/><br/>
+
+                <label for="room-code">{"Room code of your friend"}</label><br/>
+                <input id="room-code" ref=self.room_code.clone() type="text" placeholder="purple-quiet-otter-4271"
+                    autocomplete="off" autocapitalize="none" spellcheck="false"/><br/>
+                {
+                    match &self.room_code_error {
+                        Some(error) => html! { <p class="connect__error">{ error }</p> },
+                        None => html! {},
+                    }
+                }
+                <button id="join-room-code" onclick=self.link.callback(|e: MouseEvent| Msg::ClickJoinRoomCode(e))>
+                    {"Join"}
+                </button>
+            </>
+        }
+    }
+
+    fn view_room_form(&self) -> Html {
+        html! {
+            <form class="connect__form">
+                <p>{"You are invited to a conversation."}</p>
+                <label for="name">{"Your Name"}</label><br/>
+                <input id="name" ref=self.my_name.clone() type="text" value=self.props.display_name.clone()/><br/><br/>
+
+                { self.view_candidate_policy() }
+
+                <button id="join-room" onclick=self.link.callback(|e: MouseEvent| Msg::ClickJoinRoom(e))>
+                    {"Join"}
+                </button><br/><br/>
+                <a href="#/">{"Connect another way"}</a>
+            </form>
+        }
+    }
+
+    fn view_invite_link(&self) -> Html {
+        let room = match &self.props.joined_room {
+            Some(room) => room,
+            None => return html! {},
+        };
+        html! {
+            <>
+                {
+                    match invite_link(room) {
+                        Some(invite_link) => html! {
+                            <>
+                                <label for="invite-link">{"Send this link to your friend"}</label><br/>
+                                <input id="invite-link" type="text" readonly=true value=invite_link/><br/>
+                                <button onclick=self.link.callback(|e: MouseEvent| Msg::ClickCopyInviteLink(e))>
+                                    {"Copy"}
+                                </button><br/><br/>
+                            </>
+                        },
+                        None => html! {},
+                    }
+                }
+                {
+                    // Rooms created before the codes have long random identifiers
+                    match room.parse::<RoomCode>() {
+                        Ok(code) => html! {
+                            <p>{"Or read this code to your friend: "}<strong class="connect__room-code">{ code }</strong></p>
+                        },
+                        Err(_) => html! {},
+                    }
+                }
+            </>
+        }
+    }
+
+    fn view_manual_form(&self) -> Html {
+        html! {
+            <>
+                { self.view_candidate_policy() }
+
+                <button id="create-invitation" onclick=self.link.callback(|e: MouseEvent| Msg::ClickCreateInvitation(e))>
+                    {"Create an invitation"}
+                </button><br/><br/>
+
+                <label for="pasted-code">{"Or paste the invitation of your friend"}</label><br/>
+                <textarea id="pasted-code" class="connect__code" ref=self.pasted_code.clone()></textarea><br/>
+                <button id="accept-invitation" onclick=self.link.callback(|e: MouseEvent| Msg::ClickAcceptInvitation(e))>
+                    {"Answer the invitation"}
+                </button><br/><br/>
+                { self.view_scanner() }
+            </>
+        }
+    }
+
+    fn view_demo_form(&self) -> Html {
+        html! {
+            <>
+                <p>{"Chat with a bot running in this page, it answers with your own messages."}</p>
+                <button id="start-demo" onclick=self.link.callback(|e: MouseEvent| Msg::ClickStartDemo(e))>
+                    {"Start the demo"}
+                </button>
+            </>
+        }
+    }
+
+    fn view_session_code(&self) -> Html {
+        let role = match self.manual_role() {
+            Some(role) => role,
+            None => return html! {},
+        };
+        let session_code = match &self.props.session_code {
+            Some(session_code) => session_code.clone(),
+            None => return html! { <p>{"Gathering network information..."}</p> },
+        };
+
+        html! {
+            <>
+                <label for="session-code">
+                    { if role == ManualRole::Inviting { "Send this invitation to your friend" } else { "Send this answer back to your friend" } }
+                </label><br/>
+                <QrCode data=session_code.clone()/>
+                <textarea id="session-code" class="connect__code" readonly=true value=session_code></textarea><br/>
+                <button onclick=self.link.callback(|e: MouseEvent| Msg::ClickCopySessionCode(e))>
+                    {"Copy"}
+                </button><br/><br/>
+                {
+                    if role == ManualRole::Inviting {
+                        html! {
+                            <>
+                                <label for="pasted-code">{"Paste the answer of your friend"}</label><br/>
+                                <textarea id="pasted-code" class="connect__code" ref=self.pasted_code.clone()></textarea><br/>
+                                <button id="complete-invitation" onclick=self.link.callback(|e: MouseEvent| Msg::ClickCompleteInvitation(e))>
+                                    {"Connect"}
+                                </button><br/><br/>
+                                { self.view_scanner() }
+                            </>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+            </>
+        }
+    }
 }
 
 impl Component for Connect {
@@ -30,13 +348,27 @@ impl Component for Connect {
             link,
             my_name: NodeRef::default(),
             other_name: NodeRef::default(),
+            candidate_policy: NodeRef::default(),
+            pasted_code: NodeRef::default(),
+            room_code: NodeRef::default(),
+            room_code_error: None,
+            signaling: Signaling::Server,
+            manual_roles: HashMap::new(),
+            scanning: false,
             props,
-            display_connect: true,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
+            Msg::ChangeSignaling(ChangeData::Select(select)) => {
+                self.signaling = match select.value().as_str() {
+                    "manual" => Signaling::Manual,
+                    "demo" => Signaling::Demo,
+                    _ => Signaling::Server,
+                };
+            }
+            Msg::ChangeSignaling(_) => return false,
             Msg::ClickConnect(e) => {
                 e.prevent_default();
 
@@ -46,40 +378,155 @@ impl Component for Connect {
                 ) {
                     log::debug!("My name: {}", input_name.value());
                     log::debug!("Peer name: {}", other_name.value());
-                    // Emit this in lib.rs
-                    self.props.on_connect.emit(Participants {
+                    self.emit(ConnectMode::Server(Participants {
                         user_from: input_name.value(),
                         user_to: other_name.value(),
-                    });
-                    self.display_connect = false;
+                        room: None,
+                    }));
                 } else {
                     log::error!("Both names are mandatory");
                 };
             }
+            Msg::ClickCreateRoom(e) => {
+                e.prevent_default();
+                match RoomCode::generate() {
+                    Ok(code) => self.join_room(code.to_string(), true),
+                    Err(err) => log::error!("Could not create a room: {}", err),
+                }
+            }
+            Msg::ClickJoinRoom(e) => {
+                e.prevent_default();
+                if let Some(room) = self.props.room.clone() {
+                    self.join_room(room, false);
+                }
+            }
+            Msg::ClickJoinRoomCode(e) => {
+                e.prevent_default();
+                let code = self.room_code.cast::<HtmlInputElement>().map(|input| input.value());
+                match code.unwrap_or_default().parse::<RoomCode>() {
+                    Ok(code) => {
+                        self.room_code_error = None;
+                        self.join_room(code.to_string(), false);
+                    }
+                    Err(err) => self.room_code_error = Some(err.user_message()),
+                }
+            }
+            Msg::ClickCopyInviteLink(e) => {
+                e.prevent_default();
+                if let Some(invite_link) = self.props.joined_room.as_deref().and_then(invite_link) {
+                    copy_to_clipboard(&invite_link);
+                }
+                return false;
+            }
+            Msg::ClickCreateInvitation(e) => {
+                e.prevent_default();
+                self.set_manual_role(ManualRole::Inviting);
+                self.emit(ConnectMode::CreateInvitation);
+            }
+            Msg::ClickAcceptInvitation(e) => {
+                e.prevent_default();
+                match self.pasted_code() {
+                    Some(code) => {
+                        self.set_manual_role(ManualRole::Answering);
+                        self.emit(ConnectMode::AcceptInvitation(code));
+                    }
+                    None => log::error!("The invitation is mandatory"),
+                }
+            }
+            Msg::ClickCompleteInvitation(e) => {
+                e.prevent_default();
+                match self.pasted_code() {
+                    Some(code) => self.emit(ConnectMode::CompleteInvitation(code)),
+                    None => log::error!("The answer is mandatory"),
+                }
+            }
+            Msg::ClickCopySessionCode(e) => {
+                e.prevent_default();
+                if let Some(session_code) = &self.props.session_code {
+                    copy_to_clipboard(session_code);
+                }
+                return false;
+            }
+            Msg::ClickStartDemo(e) => {
+                e.prevent_default();
+                self.emit(ConnectMode::Demo);
+            }
+            Msg::ClickScan(e) => {
+                e.prevent_default();
+                self.scanning = !self.scanning;
+            }
+            Msg::Scanned(code) => {
+                self.scanning = false;
+                // The offerer scans the answer, anyone else scans an invitation
+                if self.manual_role() == Some(ManualRole::Inviting) {
+                    self.emit(ConnectMode::CompleteInvitation(code));
+                } else {
+                    self.set_manual_role(ManualRole::Answering);
+                    self.emit(ConnectMode::AcceptInvitation(code));
+                }
+            }
+            Msg::ClickDisconnect(e) => {
+                e.prevent_default();
+                self.props.on_disconnect.emit(());
+            }
         };
         true
     }
 
-    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
-        // Should only return "true" if new properties are different to
-        // previously received properties.
-        // This component has no properties so we will always return "false".
-        false
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        let other_conversation = self.props.conversation != props.conversation;
+        let should_render = other_conversation
+            || self.props.connected != props.connected
+            || self.props.session_code != props.session_code
+            || self.props.room != props.room
+            || self.props.joined_room != props.joined_room
+            || self.props.display_name != props.display_name;
+        if !props.connected {
+            self.manual_roles.remove(&props.conversation);
+        }
+        if other_conversation || props.connected != self.props.connected {
+            self.scanning = false;
+        }
+        if other_conversation {
+            self.room_code_error = None;
+        }
+        self.props = props;
+        should_render
     }
 
     fn view(&self) -> Html {
-        html! {
-            <>
-                <form class={format!("connect__form {}", if !self.display_connect {"connected"} else {""})}>
-                    <label for="name">{"Your Name"}</label><br/>
-                    <input id="name" ref=self.my_name.clone() type="text"/><br/><br/>
+        if self.props.connected {
+            return html! {
+                <form class="connect__form">
+                    { self.view_invite_link() }
+                    { self.view_session_code() }
+                    <button id="disconnect" onclick=self.link.callback(|e: MouseEvent| Msg::ClickDisconnect(e))>
+                        {"Disconnect"}
+                    </button>
+                </form>
+            };
+        }
 
-                    <label for="recipient">{"Your friend's name"}</label><br/>
-                    <input id="recipient" ref=self.other_name.clone() type="text"/><br/><br/>
+        if self.props.room.is_some() {
+            return self.view_room_form();
+        }
 
-                    <button id="connect" onclick=self.link.callback(|e: MouseEvent| Msg::ClickConnect(e))>
-                        {"Connect"}
-                    </button>
+        html! {
+            <>
+                <form class="connect__form">
+                    <label for="signaling">{"Connection"}</label><br/>
+                    <select id="signaling" onchange=self.link.callback(|e: ChangeData| Msg::ChangeSignaling(e))>
+                        <option value="server">{"Signaling server"}</option>
+                        <option value="manual">{"Manual (copy and paste, no server)"}</option>
+                        <option value="demo">{"Demo (echo bot, no server)"}</option>
+                    </select><br/><br/>
+                    {
+                        match self.signaling {
+                            Signaling::Server => self.view_server_form(),
+                            Signaling::Manual => self.view_manual_form(),
+                            Signaling::Demo => self.view_demo_form(),
+                        }
+                    }
                 </form>
             </>
         }
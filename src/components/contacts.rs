@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+
+use web_sys::HtmlInputElement;
+use yew::agent::{Bridged, Dispatched, Dispatcher};
+use yew::prelude::*;
+use yew::Bridge;
+
+use crate::event_bus::{Event, EventBus, PresenceUpdate, Request, Topic};
+use crate::peer_agent::{PeerAgent, PeerRequest};
+use crate::utils::contacts::ContactBook;
+use crate::utils::socket::PresenceSubscription;
+
+pub struct Contacts {
+    link: ComponentLink<Self>,
+    new_contact: NodeRef,
+    online: HashSet<String>,
+    peer: Dispatcher<PeerAgent>,
+    props: ContactsProps,
+    _event_bus: Box<dyn Bridge<EventBus>>,
+}
+
+#[derive(Properties, Clone)]
+pub struct ContactsProps {
+    pub contact_book: ContactBook,
+    pub on_add: Callback<String>,
+    pub on_remove: Callback<String>,
+    /// Connects to the contact in a conversation
+    pub on_connect: Callback<String>,
+}
+
+pub enum Msg {
+    Presence(PresenceUpdate),
+    Add(MouseEvent),
+    Remove(String),
+    Connect(String),
+    Ignore,
+}
+
+impl Contacts {
+    // Known once the user connected with their name
+    fn watch(&mut self) {
+        if let Some(me) = &self.props.contact_book.me {
+            self.peer.send(PeerRequest::WatchPresence(PresenceSubscription {
+                user: me.clone(),
+                contacts: self.props.contact_book.contacts.clone(),
+            }));
+        }
+    }
+
+    fn view_contact(&self, name: &str) -> Html {
+        let (presence_class, presence_title) = if self.online.contains(name) {
+            ("contacts__presence contacts__presence--online", "Online")
+        } else {
+            ("contacts__presence", "Offline")
+        };
+        let connect_name = name.to_string();
+        let remove_name = name.to_string();
+        html! {
+            <li class="contacts__item">
+                <span class=presence_class title=presence_title></span>
+                <span class="contacts__name">{ name }</span>
+                <button class="contacts__connect" disabled=self.props.contact_book.me.is_none()
+                    onclick=self.link.callback(move |_| Msg::Connect(connect_name.clone()))>
+                    {"Connect"}
+                </button>
+                <button class="contacts__remove material-icons" title="Remove the contact"
+                    onclick=self.link.callback(move |_| Msg::Remove(remove_name.clone()))>
+                    {"close"}
+                </button>
+            </li>
+        }
+    }
+}
+
+impl Component for Contacts {
+    type Message = Msg;
+    type Properties = ContactsProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut event_bus = EventBus::bridge(link.callback(|event| match event {
+            Event::Presence(update) => Msg::Presence(update),
+            // Not subscribed
+            _ => Msg::Ignore,
+        }));
+        event_bus.send(Request::Subscribe(vec![Topic::Presence]));
+        let mut contacts = Self {
+            link,
+            new_contact: NodeRef::default(),
+            online: HashSet::new(),
+            peer: PeerAgent::dispatcher(),
+            props,
+            _event_bus: event_bus,
+        };
+        contacts.watch();
+        contacts
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Presence(update) => {
+                if update.online {
+                    self.online.insert(update.user)
+                } else {
+                    self.online.remove(&update.user)
+                }
+            }
+            Msg::Add(e) => {
+                e.prevent_default();
+                if let Some(input) = self.new_contact.cast::<HtmlInputElement>() {
+                    self.props.on_add.emit(input.value());
+                    input.set_value("");
+                }
+                false
+            }
+            Msg::Remove(name) => {
+                self.props.on_remove.emit(name);
+                false
+            }
+            Msg::Connect(name) => {
+                self.props.on_connect.emit(name);
+                false
+            }
+            Msg::Ignore => false,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props.contact_book == props.contact_book {
+            return false;
+        }
+        self.props = props;
+        self.watch();
+        true
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <section class="contacts">
+                <h3>{"Contacts"}</h3>
+                {
+                    if self.props.contact_book.me.is_none() {
+                        html! { <p class="contacts__hint">{"Connect once with your name to see who is online."}</p> }
+                    } else {
+                        html! {}
+                    }
+                }
+                <ul class="contacts__list">
+                    { for self.props.contact_book.contacts.iter().map(|name| self.view_contact(name)) }
+                </ul>
+                <form class="contacts__form">
+                    <input id="new-contact" type="text" placeholder="Name of a friend" ref=self.new_contact.clone()/>
+                    <button id="add-contact" onclick=self.link.callback(|e: MouseEvent| Msg::Add(e))>{"Add"}</button>
+                </form>
+            </section>
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use yew::agent::Bridged;
+use yew::prelude::*;
+use yew::Bridge;
+
+use crate::utils::callbacks::live_closures;
+use crate::utils::clipboard::copy_to_clipboard;
+use crate::utils::stats::{CandidateStats, ConnectionStats};
+use crate::peer_agent::{ConversationId, PeerAgent, PeerRequest, PeerResponse};
+
+const POLLING_INTERVAL_MS: i32 = 2_000;
+// One minute of history with the default polling interval
+const MAX_SAMPLES: usize = 30;
+const CHART_WIDTH: f64 = 120.0;
+const CHART_HEIGHT: f64 = 40.0;
+
+pub struct Diagnostics {
+    link: ComponentLink<Self>,
+    peer: Box<dyn Bridge<PeerAgent>>,
+    samples: VecDeque<ConnectionStats>,
+    interval: Option<i32>,
+    props: DiagnosticsProps,
+    _on_interval: Closure<dyn FnMut(JsValue)>,
+}
+
+#[derive(Properties, Clone)]
+pub struct DiagnosticsProps {
+    pub conversation: ConversationId,
+}
+
+pub enum Msg {
+    Poll,
+    Stats(ConversationId, Box<ConnectionStats>),
+    CopyJson,
+    Ignore,
+}
+
+impl Diagnostics {
+    fn view_candidate(candidate: &Option<CandidateStats>) -> Html {
+        match candidate {
+            Some(candidate) => html! {
+                <>{ format!("{} {}:{} ({})", candidate.candidate_type, candidate.address, candidate.port, candidate.protocol) }</>
+            },
+            None => html! { <>{"-"}</> },
+        }
+    }
+
+    fn view_chart(&self) -> Html {
+        let rtts: Vec<f64> = self
+            .samples
+            .iter()
+            .map(|sample| {
+                sample
+                    .selected_pair
+                    .as_ref()
+                    .and_then(|pair| pair.round_trip_time_ms)
+                    .unwrap_or_default()
+            })
+            .collect();
+        let max_rtt = rtts.iter().cloned().fold(1.0, f64::max);
+        let step = CHART_WIDTH / (MAX_SAMPLES - 1) as f64;
+        let points = rtts
+            .iter()
+            .enumerate()
+            .map(|(index, rtt)| {
+                format!("{:.1},{:.1}", index as f64 * step, CHART_HEIGHT - rtt / max_rtt * CHART_HEIGHT)
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        html! {
+            <svg class="diagnostics__chart" viewBox=format!("0 0 {} {}", CHART_WIDTH, CHART_HEIGHT) preserveAspectRatio="none">
+                <polyline points=points />
+            </svg>
+        }
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..=1_023 => format!("{} B", bytes),
+        1_024..=1_048_575 => format!("{:.1} KiB", bytes as f64 / 1_024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+impl Component for Diagnostics {
+    type Message = Msg;
+    type Properties = DiagnosticsProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let peer = PeerAgent::bridge(link.callback(|response| match response {
+            PeerResponse::Stats(conversation, stats) => Msg::Stats(conversation, stats),
+            _ => Msg::Ignore,
+        }));
+        let poll = link.callback(|_: ()| Msg::Poll);
+        let on_interval: Closure<dyn FnMut(JsValue)> =
+            Closure::wrap(Box::new(move |_: JsValue| poll.emit(())));
+        let interval = web_sys::window().and_then(|window| {
+            window
+                .set_interval_with_callback_and_timeout_and_arguments_0(
+                    on_interval.as_ref().unchecked_ref(),
+                    POLLING_INTERVAL_MS,
+                )
+                .ok()
+        });
+        link.send_message(Msg::Poll);
+
+        Self {
+            link,
+            peer,
+            samples: VecDeque::with_capacity(MAX_SAMPLES),
+            interval,
+            props,
+            _on_interval: on_interval,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Poll => {
+                self.peer.send(PeerRequest::GetStats(self.props.conversation));
+                false
+            }
+            // Polled before the conversation changed
+            Msg::Stats(conversation, _) if conversation != self.props.conversation => false,
+            Msg::Stats(_, stats) => {
+                if self.samples.len() == MAX_SAMPLES {
+                    self.samples.pop_front();
+                }
+                self.samples.push_back(*stats);
+                true
+            }
+            Msg::CopyJson => {
+                match serde_json::to_string_pretty(&self.samples) {
+                    Ok(json) => copy_to_clipboard(&json),
+                    Err(err) => log::error!("Could not serialize the stats {:?}", err),
+                }
+                false
+            }
+            Msg::Ignore => false,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if props.conversation == self.props.conversation {
+            return false;
+        }
+        self.props = props;
+        self.samples.clear();
+        self.link.send_message(Msg::Poll);
+        true
+    }
+
+    fn destroy(&mut self) {
+        if let (Some(interval), Some(window)) = (self.interval.take(), web_sys::window()) {
+            window.clear_interval_with_handle(interval);
+        }
+    }
+
+    fn view(&self) -> Html {
+        let latest = self.samples.back().cloned().unwrap_or_default();
+        let (local, remote, rtt) = match &latest.selected_pair {
+            Some(pair) => (pair.local.clone(), pair.remote.clone(), pair.round_trip_time_ms),
+            None => (None, None, None),
+        };
+
+        html! {
+            <section class="diagnostics">
+                <h3>{"Diagnostics"}</h3>
+                <dl>
+                    <dt>{"Local candidate"}</dt>
+                    <dd>{ Diagnostics::view_candidate(&local) }</dd>
+                    <dt>{"Remote candidate"}</dt>
+                    <dd>{ Diagnostics::view_candidate(&remote) }</dd>
+                    <dt>{"Round trip time"}</dt>
+                    <dd>{ rtt.map_or(String::from("-"), |rtt| format!("{:.0} ms", rtt)) }</dd>
+                    <dt>{"Packet loss"}</dt>
+                    <dd>{ latest.packet_loss.map_or(String::from("n/a"), |loss| format!("{:.1} %", loss * 100.0)) }</dd>
+                    <dt>{"Connectivity check loss"}</dt>
+                    <dd>{ latest.check_loss.map_or(String::from("-"), |loss| format!("{:.1} %", loss * 100.0)) }</dd>
+                    { for latest.data_channels.iter().map(|data_channel| html! {
+                        <>
+                            <dt>{ format!("Data channel {}", data_channel.label) }</dt>
+                            <dd>{ format!("{} sent / {} received", format_bytes(data_channel.bytes_sent), format_bytes(data_channel.bytes_received)) }</dd>
+                        </>
+                    }) }
+                    <dt>{"Live closures"}</dt>
+                    <dd>{ live_closures() }</dd>
+                </dl>
+                { self.view_chart() }
+                <button onclick=self.link.callback(|_| Msg::CopyJson)>{"Copy as JSON"}</button>
+            </section>
+        }
+    }
+}
@@ -1,3 +1,10 @@
 pub mod chat_message;
 pub mod chatbox;
 pub mod connect;
+pub mod contacts;
+pub mod diagnostics;
+pub mod qr_code;
+pub mod qr_scanner;
+pub mod settings;
+pub mod sidebar;
+pub mod toast;
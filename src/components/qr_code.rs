@@ -0,0 +1,64 @@
+use yew::prelude::*;
+
+use crate::utils::qr_code::QrMatrix;
+
+/// Renders `data` as a QR code so another device can scan it
+pub struct QrCode {
+    props: QrCodeProps,
+    matrix: Option<QrMatrix>,
+}
+
+#[derive(Properties, Clone)]
+pub struct QrCodeProps {
+    pub data: String,
+}
+
+impl QrCode {
+    fn encode(data: &str) -> Option<QrMatrix> {
+        match QrMatrix::encode(data) {
+            Ok(matrix) => Some(matrix),
+            Err(err) => {
+                log::warn!("Could not create the QR code {:?}", err);
+                None
+            }
+        }
+    }
+}
+
+impl Component for QrCode {
+    type Message = ();
+    type Properties = QrCodeProps;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        let matrix = QrCode::encode(&props.data);
+        Self { props, matrix }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props.data == props.data {
+            return false;
+        }
+        self.matrix = QrCode::encode(&props.data);
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        match &self.matrix {
+            Some(matrix) => {
+                let size = matrix.size();
+                html! {
+                    <svg class="qr-code" viewBox=format!("0 0 {} {}", size, size) shape-rendering="crispEdges">
+                        <rect class="qr-code__background" width=size.to_string() height=size.to_string()/>
+                        <path class="qr-code__modules" d=matrix.svg_path()/>
+                    </svg>
+                }
+            }
+            None => html! { <p>{"This code is too long for a QR code, copy it instead."}</p> },
+        }
+    }
+}
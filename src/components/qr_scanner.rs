@@ -0,0 +1,233 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use js_sys::{Array, Function, Object, Promise, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{HtmlVideoElement, MediaStream, MediaStreamConstraints, MediaStreamTrack};
+use yew::prelude::*;
+
+use crate::utils::error::{js_error_message, CustomError};
+
+const SCAN_INTERVAL_MS: i32 = 300;
+
+/// Reads a QR code with the camera, using the `BarcodeDetector` API of the browser.
+/// Neither the API nor the camera are always available, the user can still paste the code.
+pub struct QrScanner {
+    link: ComponentLink<Self>,
+    props: QrScannerProps,
+    video: NodeRef,
+    detector: Option<JsValue>,
+    stream: Option<MediaStream>,
+    is_detecting: bool,
+    interval: Option<i32>,
+    error: Option<String>,
+    // Cleared when destroyed, a camera granted afterwards is turned off right away
+    alive: Rc<Cell<bool>>,
+    _on_interval: Closure<dyn FnMut(JsValue)>,
+}
+
+#[derive(Properties, Clone)]
+pub struct QrScannerProps {
+    pub on_scan: Callback<String>,
+}
+
+pub enum Msg {
+    StreamStarted(MediaStream),
+    Scan,
+    Detected(Option<String>),
+    Error(CustomError),
+}
+
+fn stop_tracks(stream: &MediaStream) {
+    stream
+        .get_tracks()
+        .iter()
+        .for_each(|track| track.unchecked_into::<MediaStreamTrack>().stop());
+}
+
+impl QrScanner {
+    // BarcodeDetector is not part of web-sys, it is reached dynamically
+    fn create_detector() -> Option<JsValue> {
+        let window = web_sys::window()?;
+        let constructor = Reflect::get(&window, &JsValue::from_str("BarcodeDetector"))
+            .ok()?
+            .dyn_into::<Function>()
+            .ok()?;
+        let options = Object::new();
+        let formats = Array::of1(&JsValue::from_str("qr_code"));
+        Reflect::set(&options, &JsValue::from_str("formats"), &formats).ok()?;
+        Reflect::construct(&constructor, &Array::of1(&options)).ok()
+    }
+
+    fn start_camera(&self) {
+        let media_devices = match web_sys::window().map(|window| window.navigator().media_devices()) {
+            Some(Ok(media_devices)) => media_devices,
+            _ => {
+                self.link.send_message(Msg::Error(CustomError::MediaError(String::from("no camera is available"))));
+                return;
+            }
+        };
+
+        // Prefer the back camera on phones
+        let video_constraints = Object::new();
+        let _ = Reflect::set(
+            &video_constraints,
+            &JsValue::from_str("facingMode"),
+            &JsValue::from_str("environment"),
+        );
+        let constraints = MediaStreamConstraints::new();
+        constraints.set_audio(&JsValue::FALSE);
+        constraints.set_video(&video_constraints);
+
+        let user_media = match media_devices.get_user_media_with_constraints(&constraints) {
+            Ok(user_media) => user_media,
+            Err(err) => {
+                self.link.send_message(Msg::Error(CustomError::MediaError(js_error_message(&err))));
+                return;
+            }
+        };
+        let link = self.link.clone();
+        let alive = self.alive.clone();
+        spawn_local(async move {
+            match JsFuture::from(user_media).await {
+                Ok(stream) if !alive.get() => stop_tracks(&stream.unchecked_into()),
+                Ok(stream) => link.send_message(Msg::StreamStarted(stream.unchecked_into())),
+                Err(err) => link.send_message(Msg::Error(CustomError::MediaError(js_error_message(&err)))),
+            }
+        });
+    }
+
+    fn detect(&mut self) {
+        let (detector, video) = match (&self.detector, self.video.cast::<HtmlVideoElement>()) {
+            (Some(detector), Some(video)) => (detector, video),
+            _ => return,
+        };
+        let detection = Reflect::get(detector, &JsValue::from_str("detect"))
+            .ok()
+            .and_then(|detect| detect.dyn_into::<Function>().ok())
+            .and_then(|detect| detect.call1(detector, &video).ok())
+            .and_then(|detection| detection.dyn_into::<Promise>().ok());
+        let detection = match detection {
+            Some(detection) => detection,
+            None => return,
+        };
+
+        self.is_detecting = true;
+        let link = self.link.clone();
+        spawn_local(async move {
+            // Resolves with an array of DetectedBarcode, rawValue holds the content
+            let raw_value = JsFuture::from(detection).await.ok().and_then(|barcodes| {
+                Array::from(&barcodes)
+                    .iter()
+                    .find_map(|barcode| Reflect::get(&barcode, &JsValue::from_str("rawValue")).ok()?.as_string())
+            });
+            link.send_message(Msg::Detected(raw_value));
+        });
+    }
+
+    fn stop(&mut self) {
+        if let (Some(interval), Some(window)) = (self.interval.take(), web_sys::window()) {
+            window.clear_interval_with_handle(interval);
+        }
+        if let Some(stream) = self.stream.take() {
+            stop_tracks(&stream);
+        }
+    }
+}
+
+impl Component for QrScanner {
+    type Message = Msg;
+    type Properties = QrScannerProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let scan = link.callback(|_: ()| Msg::Scan);
+        let on_interval: Closure<dyn FnMut(JsValue)> = Closure::wrap(Box::new(move |_: JsValue| scan.emit(())));
+        let detector = QrScanner::create_detector();
+        let error = match detector {
+            Some(_) => None,
+            None => Some(String::from("This browser cannot scan QR codes, paste the code instead")),
+        };
+
+        Self {
+            link,
+            props,
+            video: NodeRef::default(),
+            detector,
+            stream: None,
+            is_detecting: false,
+            interval: None,
+            error,
+            alive: Rc::new(Cell::new(true)),
+            _on_interval: on_interval,
+        }
+    }
+
+    fn rendered(&mut self, first_render: bool) {
+        if first_render && self.detector.is_some() {
+            self.start_camera();
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::StreamStarted(stream) => {
+                if let Some(video) = self.video.cast::<HtmlVideoElement>() {
+                    video.set_src_object(Some(&stream));
+                    let _ = video.play();
+                }
+                self.stream = Some(stream);
+                self.interval = web_sys::window().and_then(|window| {
+                    window
+                        .set_interval_with_callback_and_timeout_and_arguments_0(
+                            self._on_interval.as_ref().unchecked_ref(),
+                            SCAN_INTERVAL_MS,
+                        )
+                        .ok()
+                });
+                false
+            }
+            Msg::Scan => {
+                // The previous detection may still be running on slow devices
+                if !self.is_detecting {
+                    self.detect();
+                }
+                false
+            }
+            Msg::Detected(raw_value) => {
+                self.is_detecting = false;
+                if let Some(raw_value) = raw_value {
+                    self.stop();
+                    self.props.on_scan.emit(raw_value);
+                }
+                false
+            }
+            Msg::Error(error) => {
+                log::error!("{}", error);
+                self.stop();
+                self.error = Some(error.user_message());
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        false
+    }
+
+    fn destroy(&mut self) {
+        self.alive.set(false);
+        self.stop();
+    }
+
+    fn view(&self) -> Html {
+        match &self.error {
+            Some(error) => html! { <p class="qr-scanner__error">{ error }</p> },
+            None => html! {
+                <video class="qr-scanner" ref=self.video.clone() autoplay=true muted=true playsinline=true></video>
+            },
+        }
+    }
+}
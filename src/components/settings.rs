@@ -0,0 +1,224 @@
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+use crate::utils::error::CustomError;
+use crate::utils::settings::{IceServer, NotificationSettings, SendKey, Settings};
+use crate::utils::theme::{CustomTheme, Theme};
+
+pub struct SettingsPanel {
+    link: ComponentLink<Self>,
+    display_name: NodeRef,
+    theme: NodeRef,
+    custom_theme: NodeRef,
+    info: NodeRef,
+    warnings: NodeRef,
+    unread_badges: NodeRef,
+    send_key: NodeRef,
+    ice_servers: NodeRef,
+    history_limit: NodeRef,
+    error: Option<String>,
+    props: SettingsProps,
+}
+
+#[derive(Properties, Clone)]
+pub struct SettingsProps {
+    pub settings: Settings,
+    pub on_save: Callback<Settings>,
+    /// Back to the default settings, even over those of a newer version
+    pub on_reset: Callback<()>,
+    pub on_close: Callback<()>,
+}
+
+pub enum Msg {
+    Save(MouseEvent),
+    Reset(MouseEvent),
+    Close(MouseEvent),
+}
+
+fn input_value(node_ref: &NodeRef) -> String {
+    node_ref.cast::<HtmlInputElement>().map(|input| input.value()).unwrap_or_default()
+}
+
+fn checked(node_ref: &NodeRef) -> bool {
+    node_ref.cast::<HtmlInputElement>().is_some_and(|input| input.checked())
+}
+
+fn select_value(node_ref: &NodeRef) -> String {
+    node_ref.cast::<HtmlSelectElement>().map(|select| select.value()).unwrap_or_default()
+}
+
+fn textarea_value(node_ref: &NodeRef) -> String {
+    node_ref.cast::<HtmlTextAreaElement>().map(|textarea| textarea.value()).unwrap_or_default()
+}
+
+impl SettingsPanel {
+    fn read_form(&self) -> Result<Settings, CustomError> {
+        let ice_servers = textarea_value(&self.ice_servers)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(IceServer::parse_line)
+            .collect::<Result<Vec<IceServer>, CustomError>>()?;
+        let history_limit = input_value(&self.history_limit);
+        let history_limit = match history_limit.trim() {
+            "" => None,
+            limit => Some(limit.parse::<usize>().map_err(|_| {
+                CustomError::SettingsError(format!("{} is not a number of messages", limit))
+            })?),
+        };
+        let custom_theme = textarea_value(&self.custom_theme);
+        let custom_theme = match custom_theme.trim() {
+            "" => None,
+            json => Some(CustomTheme::parse(json)?),
+        };
+        let settings = Settings {
+            display_name: input_value(&self.display_name).trim().to_string(),
+            theme: Theme::from_value(&select_value(&self.theme)).unwrap_or(self.props.settings.theme),
+            custom_theme,
+            notifications: NotificationSettings {
+                info: checked(&self.info),
+                warnings: checked(&self.warnings),
+                unread_badges: checked(&self.unread_badges),
+            },
+            send_key: match select_value(&self.send_key).as_str() {
+                "ctrl-enter" => SendKey::CtrlEnter,
+                _ => SendKey::Enter,
+            },
+            ice_servers,
+            history_limit,
+            ..self.props.settings.clone()
+        };
+        settings.validate()?;
+        Ok(settings)
+    }
+}
+
+impl Component for SettingsPanel {
+    type Message = Msg;
+    type Properties = SettingsProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            display_name: NodeRef::default(),
+            theme: NodeRef::default(),
+            custom_theme: NodeRef::default(),
+            info: NodeRef::default(),
+            warnings: NodeRef::default(),
+            unread_badges: NodeRef::default(),
+            send_key: NodeRef::default(),
+            ice_servers: NodeRef::default(),
+            history_limit: NodeRef::default(),
+            error: None,
+            props,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Save(e) => {
+                e.prevent_default();
+                match self.read_form() {
+                    Ok(settings) => {
+                        self.error = None;
+                        self.props.on_save.emit(settings);
+                    }
+                    Err(err) => self.error = Some(err.user_message()),
+                }
+                true
+            }
+            Msg::Reset(e) => {
+                e.prevent_default();
+                self.error = None;
+                self.props.on_reset.emit(());
+                true
+            }
+            Msg::Close(e) => {
+                e.prevent_default();
+                self.props.on_close.emit(());
+                false
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        let should_render = self.props.settings != props.settings;
+        self.props = props;
+        should_render
+    }
+
+    fn view(&self) -> Html {
+        let settings = &self.props.settings;
+        let ice_servers = settings
+            .ice_servers
+            .iter()
+            .map(IceServer::to_line)
+            .collect::<Vec<String>>()
+            .join("\n");
+        let history_limit = settings.history_limit.map(|limit| limit.to_string()).unwrap_or_default();
+        let custom_theme = settings
+            .custom_theme
+            .as_ref()
+            .and_then(|custom_theme| serde_json::to_string_pretty(custom_theme).ok())
+            .unwrap_or_default();
+
+        html! {
+            <form class="settings">
+                <h3>{"Settings"}</h3>
+
+                <label for="settings-name">{"Your name"}</label><br/>
+                <input id="settings-name" ref=self.display_name.clone() type="text" value=settings.display_name.clone()/><br/><br/>
+
+                <label for="settings-theme">{"Theme"}</label><br/>
+                <select id="settings-theme" ref=self.theme.clone()>
+                    { for Theme::ALL.iter().map(|theme| html! {
+                        <option value=theme.value() selected={ *theme == settings.theme }>{ theme.label() }</option>
+                    }) }
+                </select><br/><br/>
+
+                <label for="settings-custom-theme">{"Custom theme, as JSON"}</label><br/>
+                <textarea id="settings-custom-theme" class="settings__custom-theme" ref=self.custom_theme.clone()
+                    value=custom_theme spellcheck="false"
+                    placeholder=r##"{"name": "Night", "base": "dark", "colors": {"color-accent": "#ff9800"}}"##></textarea><br/><br/>
+
+                <fieldset class="settings__group">
+                    <legend>{"Notifications"}</legend>
+                    <label>
+                        <input id="settings-info" ref=self.info.clone() type="checkbox" checked=settings.notifications.info/>
+                        {"Show information messages"}
+                    </label><br/>
+                    <label>
+                        <input id="settings-warnings" ref=self.warnings.clone() type="checkbox" checked=settings.notifications.warnings/>
+                        {"Show warnings"}
+                    </label><br/>
+                    <label>
+                        <input id="settings-unread" ref=self.unread_badges.clone() type="checkbox" checked=settings.notifications.unread_badges/>
+                        {"Count the unread messages"}
+                    </label>
+                </fieldset><br/>
+
+                <label for="settings-send-key">{"Send messages with"}</label><br/>
+                <select id="settings-send-key" ref=self.send_key.clone()>
+                    <option value="enter" selected={ settings.send_key == SendKey::Enter }>{"Enter"}</option>
+                    <option value="ctrl-enter" selected={ settings.send_key == SendKey::CtrlEnter }>{"Ctrl+Enter"}</option>
+                </select><br/><br/>
+
+                <label for="settings-ice-servers">{"STUN and TURN servers, one per line: URL [username password]"}</label><br/>
+                <textarea id="settings-ice-servers" class="settings__ice-servers" ref=self.ice_servers.clone()
+                    value=ice_servers spellcheck="false"></textarea><br/><br/>
+
+                <label for="settings-history">{"Messages kept per conversation, empty for all"}</label><br/>
+                <input id="settings-history" ref=self.history_limit.clone() type="number" min="1" value=history_limit/><br/>
+
+                {
+                    match &self.error {
+                        Some(error) => html! { <p class="settings__error">{ error }</p> },
+                        None => html! {},
+                    }
+                }
+                <button id="save-settings" onclick=self.link.callback(|e: MouseEvent| Msg::Save(e))>{"Save"}</button>
+                <button id="reset-settings" onclick=self.link.callback(|e: MouseEvent| Msg::Reset(e))>{"Reset"}</button>
+                <button id="close-settings" onclick=self.link.callback(|e: MouseEvent| Msg::Close(e))>{"Close"}</button>
+            </form>
+        }
+    }
+}
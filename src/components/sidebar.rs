@@ -0,0 +1,104 @@
+use yew::prelude::*;
+
+use crate::peer_agent::ConversationId;
+use crate::utils::connection_state::ConnectionState;
+
+#[derive(Clone, PartialEq)]
+pub struct ConversationSummary {
+    pub id: ConversationId,
+    pub title: String,
+    pub unread: u32,
+    pub connection_state: ConnectionState,
+}
+
+pub struct Sidebar {
+    link: ComponentLink<Self>,
+    props: SidebarProps,
+}
+
+#[derive(Properties, Clone)]
+pub struct SidebarProps {
+    pub conversations: Vec<ConversationSummary>,
+    pub active: ConversationId,
+    /// From the notification settings
+    pub show_unread: bool,
+    pub on_select: Callback<ConversationId>,
+    pub on_new: Callback<()>,
+    pub on_close: Callback<ConversationId>,
+}
+
+pub enum Msg {
+    Select(ConversationId),
+    New,
+    Close(ConversationId),
+}
+
+impl Sidebar {
+    fn view_conversation(&self, conversation: &ConversationSummary) -> Html {
+        let id = conversation.id;
+        let class = if id == self.props.active {
+            "sidebar__item sidebar__item--active"
+        } else {
+            "sidebar__item"
+        };
+        html! {
+            <li class=class>
+                <button class="sidebar__select" onclick=self.link.callback(move |_| Msg::Select(id))>
+                    <span class=format!("sidebar__state status--{}", conversation.connection_state)
+                        title=conversation.connection_state.label()></span>
+                    <span class="sidebar__title">{ &conversation.title }</span>
+                    {
+                        if self.props.show_unread && conversation.unread > 0 {
+                            html! { <span class="sidebar__unread">{ conversation.unread }</span> }
+                        } else {
+                            html! {}
+                        }
+                    }
+                </button>
+                <button class="sidebar__close material-icons" title="Close the conversation"
+                    onclick=self.link.callback(move |_| Msg::Close(id))>
+                    {"close"}
+                </button>
+            </li>
+        }
+    }
+}
+
+impl Component for Sidebar {
+    type Message = Msg;
+    type Properties = SidebarProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self { link, props }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Select(id) => self.props.on_select.emit(id),
+            Msg::New => self.props.on_new.emit(()),
+            Msg::Close(id) => self.props.on_close.emit(id),
+        }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        let should_render = self.props.conversations != props.conversations
+            || self.props.active != props.active
+            || self.props.show_unread != props.show_unread;
+        self.props = props;
+        should_render
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <nav class="sidebar">
+                <button id="new-conversation" class="sidebar__new" onclick=self.link.callback(|_| Msg::New)>
+                    {"New conversation"}
+                </button>
+                <ul class="sidebar__list">
+                    { for self.props.conversations.iter().map(|conversation| self.view_conversation(conversation)) }
+                </ul>
+            </nav>
+        }
+    }
+}
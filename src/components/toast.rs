@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use yew::prelude::*;
+
+use crate::utils::error::CustomError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn class(&self) -> &'static str {
+        match self {
+            Severity::Info => "toast--info",
+            Severity::Warning => "toast--warning",
+            Severity::Error => "toast--error",
+        }
+    }
+}
+
+/// A message for the user, outside of the conversation
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Notification {
+    pub fn new(severity: Severity, message: String) -> Self {
+        Self { severity, message }
+    }
+}
+
+impl From<&CustomError> for Notification {
+    fn from(error: &CustomError) -> Self {
+        let severity = match error {
+            CustomError::CandidateParseError(_) => Severity::Warning,
+            _ => Severity::Error,
+        };
+        Notification::new(severity, error.user_message())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Toast {
+    pub id: u32,
+    pub notification: Notification,
+}
+
+/// Shows the notifications on top of the page, until they are dismissed
+pub struct Toasts {
+    props: ToastsProps,
+}
+
+#[derive(Properties, Clone)]
+pub struct ToastsProps {
+    pub toasts: Vec<Toast>,
+    pub on_dismiss: Callback<u32>,
+}
+
+impl Component for Toasts {
+    type Message = ();
+    type Properties = ToastsProps;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        Self { props }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        let should_render = self.props.toasts != props.toasts;
+        self.props = props;
+        should_render
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <section class="toasts" role="status">
+                { for self.props.toasts.iter().map(|toast| {
+                    let id = toast.id;
+                    html! {
+                        <div class=format!("toast {}", toast.notification.severity.class())>
+                            <span class="toast__message">{ &toast.notification.message }</span>
+                            <button class="toast__dismiss material-icons" title="Dismiss"
+                                onclick=self.props.on_dismiss.reform(move |_| id)>
+                                {"close"}
+                            </button>
+                        </div>
+                    }
+                }) }
+            </section>
+        }
+    }
+}
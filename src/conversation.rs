@@ -0,0 +1,125 @@
+use crate::components::chat_message::{ChatMessage, SenderType};
+use crate::components::connect::{ConnectMode, ConnectRequest};
+use crate::peer_agent::{new_conversation_id, ConversationId};
+use crate::utils::connection_state::ConnectionState;
+
+/// An entry of the sidebar, its peer session lives in the `PeerAgent`
+pub struct Conversation {
+    pub id: ConversationId,
+    // Name of the friend or code of the room, once connected
+    pub title: Option<String>,
+    pub chat_messages: Vec<ChatMessage>,
+    pub connected: bool,
+    pub connection_state: ConnectionState,
+    pub connection_dropped: bool,
+    pub session_code: Option<String>,
+    // Shared room of the conversation
+    pub room: Option<String>,
+    // Joined again when the user comes back to its link with the history
+    pub last_room: Option<ConnectRequest>,
+    /// Messages received while another conversation was shown
+    pub unread: u32,
+    /// Typed in the chat box, not sent yet
+    pub draft: String,
+    pub scroll_top: i32,
+}
+
+impl Conversation {
+    pub fn new() -> Self {
+        Self {
+            id: new_conversation_id(),
+            title: None,
+            chat_messages: vec![],
+            connected: false,
+            connection_state: ConnectionState::New,
+            connection_dropped: false,
+            session_code: None,
+            room: None,
+            last_room: None,
+            unread: 0,
+            draft: String::new(),
+            scroll_top: 0,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or("New conversation")
+    }
+
+    pub fn on_connect(&mut self, request: &ConnectRequest) {
+        self.room = match &request.mode {
+            ConnectMode::Room { participants, .. } => participants.room.clone(),
+            _ => None,
+        };
+        if self.room.is_some() {
+            self.last_room = Some(request.clone());
+        }
+        let title = match &request.mode {
+            ConnectMode::Server(participants) => Some(participants.user_to.clone()),
+            ConnectMode::Room { participants, .. } => participants.room.clone(),
+            ConnectMode::CreateInvitation | ConnectMode::AcceptInvitation(_) => Some(String::from("Invitation")),
+            // Same session as the invitation
+            ConnectMode::CompleteInvitation(_) => None,
+            ConnectMode::Demo => Some(String::from("Echo bot")),
+        };
+        if title.is_some() {
+            self.title = title;
+        }
+    }
+
+    pub fn on_disconnected(&mut self) {
+        self.connected = false;
+        self.connection_dropped = false;
+        self.session_code = None;
+        self.chat_messages.clear();
+    }
+
+    /// `history_limit` is the number of messages kept, the oldest go first
+    pub fn on_message(&mut self, chat_message: ChatMessage, shown: bool, history_limit: Option<usize>) {
+        if !shown {
+            if let SenderType::YOU = chat_message.from {
+                self.unread += 1;
+            }
+        }
+        self.chat_messages.push(chat_message);
+        if let Some(limit) = history_limit {
+            let excess = self.chat_messages.len().saturating_sub(limit);
+            self.chat_messages.drain(..excess);
+        }
+    }
+
+    pub fn on_connection_state_changed(&mut self, state: ConnectionState) {
+        self.connection_state = state;
+        if !self.connected {
+            return;
+        }
+
+        let system_message = match state {
+            ConnectionState::Disconnected | ConnectionState::Failed if !self.connection_dropped => {
+                self.connection_dropped = true;
+                Some("The connection with your friend dropped, trying to reconnect...")
+            }
+            ConnectionState::Connected if self.session_code.is_some() => {
+                // The code has been used, no need to show it anymore
+                self.session_code = None;
+                None
+            }
+            ConnectionState::Connected if self.connection_dropped => {
+                self.connection_dropped = false;
+                Some("The connection with your friend is back.")
+            }
+            ConnectionState::Closed => {
+                // The other peer left, let the user connect to someone else
+                self.connected = false;
+                self.room = None;
+                self.connection_dropped = false;
+                self.session_code = None;
+                Some("Your friend left the conversation.")
+            }
+            _ => None,
+        };
+        if let Some(content) = system_message {
+            self.chat_messages.push(ChatMessage::new(SenderType::SYSTEM, content.to_string()));
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::panic;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use web_sys::{Document, Element};
+
+use crate::utils::connection_state::ConnectionState;
+use crate::utils::ice_candidate::CandidatePolicy;
+use crate::utils::participants::Participants;
+use crate::utils::storage;
+
+const REPORT_KEY: &str = "prust-crash-report";
+const OVERLAY_ID: &str = "crash";
+const REPORT_ID: &str = "crash-report";
+
+// The overlay works without the wasm module, it may be in any state after a panic
+const COPY_SCRIPT: &str = "navigator.clipboard.writeText(document.getElementById('crash-report').value)";
+const REJOIN_SCRIPT: &str =
+    "var query = new URLSearchParams(location.search); query.set('rejoin', ''); location.search = query.toString()";
+const RELOAD_SCRIPT: &str = "location.reload()";
+
+/// A room to connect to again after a crash
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Rejoin {
+    pub participants: Participants,
+    pub candidate_policy: CandidatePolicy,
+    /// Whether the user created the shared room of `participants`
+    #[serde(default)]
+    pub host: bool,
+}
+
+#[derive(Default)]
+struct CrashContext {
+    // By conversation, in the order they were opened
+    rooms: BTreeMap<u32, Rejoin>,
+    connection_states: BTreeMap<u32, ConnectionState>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<CrashContext> = RefCell::new(CrashContext::default());
+}
+
+/// Saved to localStorage, to be attached to bug tickets
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub message: String,
+    pub location: Option<String>,
+    pub time: String,
+    pub version: String,
+    pub page: String,
+    pub user_agent: String,
+    /// By conversation
+    #[serde(default)]
+    pub connection_states: BTreeMap<u32, ConnectionState>,
+    #[serde(default)]
+    pub rooms: Vec<Rejoin>,
+}
+
+impl CrashReport {
+    pub fn to_text(&self) -> String {
+        let connection_states = if self.connection_states.is_empty() {
+            String::from("-")
+        } else {
+            self.connection_states
+                .iter()
+                .map(|(conversation, state)| format!("#{} {}", conversation, state))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        format!(
+            "prust crash report\n\
+             Panic: {}\n\
+             At: {}\n\
+             Time: {}\n\
+             Version: {}\n\
+             Page: {}\n\
+             Browser: {}\n\
+             Connections: {}\n",
+            self.message,
+            self.location.as_deref().unwrap_or("-"),
+            self.time,
+            self.version,
+            self.page,
+            self.user_agent,
+            connection_states
+        )
+    }
+}
+
+/// Remembers the room a conversation is in, `None` once the user left it
+pub fn remember_room(conversation: u32, rejoin: Option<Rejoin>) {
+    CONTEXT.with(|context| {
+        let rooms = &mut context.borrow_mut().rooms;
+        match rejoin {
+            Some(rejoin) => rooms.insert(conversation, rejoin),
+            None => rooms.remove(&conversation),
+        }
+    });
+}
+
+/// Remembers the state of the connection of a conversation, `None` once it is closed
+pub fn remember_state(conversation: u32, connection_state: Option<ConnectionState>) {
+    CONTEXT.with(|context| {
+        let connection_states = &mut context.borrow_mut().connection_states;
+        match connection_state {
+            Some(connection_state) => connection_states.insert(conversation, connection_state),
+            None => connection_states.remove(&conversation),
+        }
+    });
+}
+
+/// The rooms of the last crash, once: a later reload does not join them again
+pub fn take_rooms() -> Vec<Rejoin> {
+    let mut report = match storage::load::<CrashReport>(REPORT_KEY) {
+        Ok(Some(report)) => report,
+        Ok(None) => return vec![],
+        Err(err) => {
+            log::error!("Could not read the crash report: {}", err);
+            return vec![];
+        }
+    };
+    let rooms = std::mem::take(&mut report.rooms);
+    if let Err(err) = storage::save(REPORT_KEY, &report) {
+        log::error!("Could not update the crash report: {}", err);
+    }
+    rooms
+}
+
+/// Replaces the frozen page with a crash overlay on panic
+pub fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        // Still logs the panic with its stack trace in the console
+        #[cfg(feature = "console_error_panic_hook")]
+        console_error_panic_hook::hook(info);
+
+        let message = match (info.payload().downcast_ref::<&str>(), info.payload().downcast_ref::<String>()) {
+            (Some(message), _) => message.to_string(),
+            (_, Some(message)) => message.clone(),
+            _ => String::from("Unknown panic"),
+        };
+        let location = info
+            .location()
+            .map(|location| format!("{}:{}:{}", location.file(), location.line(), location.column()));
+        on_panic(message, location);
+    }));
+}
+
+fn on_panic(message: String, location: Option<String>) {
+    // The panic may come from inside a borrow of the context
+    let (rooms, connection_states) = CONTEXT.with(|context| match context.try_borrow() {
+        Ok(context) => (context.rooms.values().cloned().collect(), context.connection_states.clone()),
+        Err(_) => (vec![], BTreeMap::new()),
+    });
+    let window = web_sys::window();
+    let report = CrashReport {
+        message,
+        location,
+        time: String::from(js_sys::Date::new_0().to_iso_string()),
+        version: String::from(env!("CARGO_PKG_VERSION")),
+        page: window
+            .as_ref()
+            .and_then(|window| window.location().href().ok())
+            .unwrap_or_default(),
+        user_agent: window
+            .as_ref()
+            .and_then(|window| window.navigator().user_agent().ok())
+            .unwrap_or_default(),
+        connection_states,
+        rooms,
+    };
+
+    if let Err(err) = storage::save(REPORT_KEY, &report) {
+        log::error!("Could not save the crash report: {}", err);
+    }
+    if let Some(document) = window.and_then(|window| window.document()) {
+        if let Err(err) = show_overlay(&document, &report) {
+            log::error!("Could not show the crash overlay {:?}", err);
+        }
+    }
+}
+
+fn create(document: &Document, tag: &str, class: &str, text: &str) -> Result<Element, JsValue> {
+    let element = document.create_element(tag)?;
+    element.set_class_name(class);
+    element.set_text_content(Some(text));
+    Ok(element)
+}
+
+fn show_overlay(document: &Document, report: &CrashReport) -> Result<(), JsValue> {
+    // A panic during another one
+    if document.get_element_by_id(OVERLAY_ID).is_some() {
+        return Ok(());
+    }
+    let body = match document.body() {
+        Some(body) => body,
+        None => return Ok(()),
+    };
+
+    let overlay = create(document, "section", "crash", "")?;
+    overlay.set_id(OVERLAY_ID);
+    overlay.set_attribute("role", "alertdialog")?;
+    let dialog = create(document, "div", "crash__dialog", "")?;
+    let title = create(document, "h2", "crash__title", "Something went wrong")?;
+    dialog.append_child(&title)?;
+    let text = create(
+        document,
+        "p",
+        "crash__text",
+        "The chat stopped working. Reload to go on, and attach the report below to your bug ticket.",
+    )?;
+    dialog.append_child(&text)?;
+
+    let report_text = create(document, "textarea", "crash__report", &report.to_text())?;
+    report_text.set_id(REPORT_ID);
+    report_text.set_attribute("readonly", "")?;
+    dialog.append_child(&report_text)?;
+
+    let copy = create(document, "button", "crash__button", "Copy diagnostic report")?;
+    copy.set_attribute("onclick", COPY_SCRIPT)?;
+    dialog.append_child(&copy)?;
+    let reload = match report.rooms.len() {
+        0 => {
+            let reload = create(document, "button", "crash__button", "Reload")?;
+            reload.set_attribute("onclick", RELOAD_SCRIPT)?;
+            reload
+        }
+        count => {
+            let label = if count == 1 { "Reload and rejoin room" } else { "Reload and rejoin rooms" };
+            let reload = create(document, "button", "crash__button", label)?;
+            reload.set_attribute("onclick", REJOIN_SCRIPT)?;
+            reload
+        }
+    };
+    dialog.append_child(&reload)?;
+
+    overlay.append_child(&dialog)?;
+    body.append_child(&overlay)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_report_reads_without_the_json() {
+        let report = CrashReport {
+            message: String::from("called `Option::unwrap()` on a `None` value"),
+            location: Some(String::from("src/web_rtc.rs:42:10")),
+            time: String::from("2020-11-02T10:00:00.000Z"),
+            version: String::from("0.1.0"),
+            page: String::from("http://localhost:8000/"),
+            user_agent: String::from("Firefox"),
+            connection_states: BTreeMap::new(),
+            rooms: vec![],
+        };
+        let text = report.to_text();
+
+        assert!(text.contains("Panic: called `Option::unwrap()` on a `None` value\n"));
+        assert!(text.contains("At: src/web_rtc.rs:42:10\n"));
+        assert!(text.contains("Connections: -\n"));
+    }
+
+    #[test]
+    fn the_report_tells_the_state_of_each_conversation() {
+        remember_state(0, Some(ConnectionState::Connected));
+        remember_state(3, Some(ConnectionState::Failed));
+        remember_state(4, Some(ConnectionState::Connecting));
+        remember_state(4, None);
+        let connection_states = CONTEXT.with(|context| context.borrow().connection_states.clone());
+        let report = CrashReport {
+            message: String::from("panic"),
+            location: None,
+            time: String::new(),
+            version: String::new(),
+            page: String::new(),
+            user_agent: String::new(),
+            connection_states,
+            rooms: vec![],
+        };
+
+        assert!(report.to_text().contains("Connections: #0 connected, #3 failed\n"));
+    }
+}
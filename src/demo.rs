@@ -0,0 +1,78 @@
+use std::{cell::RefCell, rc::{Rc, Weak}};
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use yew::Callback;
+
+use crate::signaling::SignalingConfig;
+use crate::utils::error::CustomError;
+use crate::utils::ice_candidate::CandidatePolicy;
+use crate::web_rtc::{WebRTC, WebRTCConfig};
+
+const DEMO_HUB: &str = "demo";
+// Long enough to see the message leave before the answer comes back
+const ECHO_DELAY_MS: i32 = 500;
+
+/// A peer living in the same page, which sends every message back.
+/// The UI can be developed and demoed with a real data channel, without server or second browser.
+pub struct DemoPeer {
+    web_rtc: Rc<RefCell<WebRTC>>,
+}
+
+impl DemoPeer {
+    /// Connects `web_rtc` to a new demo peer, their descriptions are exchanged directly
+    pub fn connect(web_rtc: Rc<RefCell<WebRTC>>) -> Result<Self, CustomError> {
+        let bot_handle: Rc<RefCell<Weak<RefCell<WebRTC>>>> = Rc::new(RefCell::new(Weak::new()));
+        let echo_handle = bot_handle.clone();
+        let bot = Rc::new(RefCell::new(WebRTC::new(WebRTCConfig {
+            signaling: SignalingConfig::InMemory(String::from(DEMO_HUB)),
+            on_message: Some(Callback::from(move |message: String| {
+                DemoPeer::echo(echo_handle.borrow().clone(), message);
+            })),
+            ..WebRTCConfig::default()
+        })));
+        *bot_handle.borrow_mut() = Rc::downgrade(&bot);
+
+        // The offer of the user is answered by the bot, and the answer goes back to the user
+        let bot_clone = bot.clone();
+        let user = web_rtc.clone();
+        let on_offer = Callback::from(move |offer: String| {
+            let user_clone = user.clone();
+            let on_answer = Callback::from(move |answer: String| {
+                if let Err(err) = WebRTC::accept_manual_answer(user_clone.clone(), &answer) {
+                    log::error!("The demo peer answer was rejected: {}", err);
+                }
+            });
+            if let Err(err) = WebRTC::accept_manual_offer(bot_clone.clone(), &offer, CandidatePolicy::All, on_answer) {
+                log::error!("The demo peer rejected the offer: {}", err);
+            }
+        });
+        WebRTC::create_manual_offer(web_rtc, CandidatePolicy::All, on_offer)?;
+
+        Ok(Self { web_rtc: bot })
+    }
+
+    fn echo(bot: Weak<RefCell<WebRTC>>, message: String) {
+        let reply = Closure::once_into_js(move || {
+            if let Some(bot) = bot.upgrade() {
+                if let Err(err) = WebRTC::send_webrtc_message(bot, &message) {
+                    log::error!("The echo bot could not answer: {}", err);
+                }
+            }
+        });
+        if let Some(window) = web_sys::window() {
+            if let Err(err) = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                reply.unchecked_ref(),
+                ECHO_DELAY_MS,
+            ) {
+                log::error!("Could not schedule the demo answer {:?}", err);
+            }
+        }
+    }
+}
+
+impl Drop for DemoPeer {
+    fn drop(&mut self) {
+        WebRTC::disconnect(self.web_rtc.clone());
+    }
+}
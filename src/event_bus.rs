@@ -1,50 +1,116 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use serde::{Deserialize, Serialize};
 use yew::worker::*;
 
 use crate::components::chat_message::ChatMessage;
+use crate::components::toast::Notification;
+use crate::peer_agent::ConversationId;
+use crate::utils::connection_state::ConnectionState;
 
-pub struct EventBus {
-    link: AgentLink<EventBus>,
-    subscribers: HashSet<HandlerId>,
+/// What an event is about, components only receive the topics they subscribed to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topic {
+    Chat,
+    Presence,
+    Connection,
+    FileTransfer,
+    Call,
+    Notification,
+}
+
+/// A user connecting to or leaving the signaling server
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PresenceUpdate {
+    pub user: String,
+    pub online: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileProgress {
+    pub name: String,
+    pub transferred: u64,
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum CallEvent {
+    Started,
+    Ended,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Event {
+    Chat(ChatMessage),
+    Presence(PresenceUpdate),
+    ConnectionState(ConversationId, ConnectionState),
+    FileProgress(FileProgress),
+    Call(CallEvent),
+    Notification(Notification),
+}
+
+impl Event {
+    pub fn topic(&self) -> Topic {
+        match self {
+            Event::Chat(_) => Topic::Chat,
+            Event::Presence(_) => Topic::Presence,
+            Event::ConnectionState(..) => Topic::Connection,
+            Event::FileProgress(_) => Topic::FileTransfer,
+            Event::Call(_) => Topic::Call,
+            Event::Notification(_) => Topic::Notification,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Request {
-    EventBusMsg(ChatMessage),
+    Subscribe(Vec<Topic>),
+    Unsubscribe(Vec<Topic>),
+    Publish(Event),
+}
+
+pub struct EventBus {
+    link: AgentLink<EventBus>,
+    subscriptions: HashMap<HandlerId, HashSet<Topic>>,
 }
 
 impl Agent for EventBus {
     type Reach = Context;
     type Message = ();
     type Input = Request;
-    type Output = ChatMessage;
+    type Output = Event;
 
     fn create(link: AgentLink<Self>) -> Self {
         Self {
             link,
-            subscribers: HashSet::new(),
+            subscriptions: HashMap::new(),
         }
     }
 
     fn update(&mut self, _msg: Self::Message) {}
 
-    fn connected(&mut self, id: HandlerId) {
-        self.subscribers.insert(id);
-    }
-
-    fn handle_input(&mut self, msg: Self::Input, _id: HandlerId) {
+    fn handle_input(&mut self, msg: Self::Input, id: HandlerId) {
         match msg {
-            Request::EventBusMsg(s) => {
-                for sub in self.subscribers.iter() {
-                    self.link.respond(*sub, s.clone());
+            Request::Subscribe(topics) => self.subscriptions.entry(id).or_default().extend(topics),
+            Request::Unsubscribe(topics) => {
+                if let Some(subscribed) = self.subscriptions.get_mut(&id) {
+                    for topic in topics {
+                        subscribed.remove(&topic);
+                    }
+                }
+            }
+            Request::Publish(event) => {
+                let topic = event.topic();
+                for (sub, topics) in self.subscriptions.iter() {
+                    if topics.contains(&topic) {
+                        self.link.respond(*sub, event.clone());
+                    }
                 }
             }
         }
     }
 
     fn disconnected(&mut self, id: HandlerId) {
-        self.subscribers.remove(&id);
+        self.subscriptions.remove(&id);
     }
 }
@@ -1,59 +1,531 @@
 #![recursion_limit = "1024"]
 
-use std::{cell::RefCell, rc::Rc};
-
-use yew::{Bridge, Component, ComponentLink, html, Html, ShouldRender};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+use yew::{Bridge, Component, ComponentLink, html, Html, NodeRef, Properties, ShouldRender};
 use yew::agent::Bridged;
 
-use components::chat_message::{ChatMessage, SenderType};
-use event_bus::EventBus;
+use components::chat_message::ChatMessage;
+use components::connect::{ConnectMode, ConnectRequest};
+use components::sidebar::ConversationSummary;
+use components::toast::{Notification, Severity, Toast};
+use conversation::Conversation;
+use event_bus::{Event, EventBus, Request, Topic};
+use peer_agent::{ConversationId, PeerAgent, PeerRequest, PeerResponse};
+use signaling::SignalingConfig;
+use utils::connection_state::ConnectionState;
+use utils::contacts::ContactBook;
+use utils::ice_candidate::CandidatePolicy;
 use utils::participants::Participants;
-use web_rtc::WebRTC;
+use utils::query::query_parameter;
+use utils::route::Route;
+use utils::settings::Settings;
+use utils::theme;
 
 mod components;
-mod utils;
-mod web_rtc;
+mod conversation;
+pub mod crash;
+mod demo;
+pub mod utils;
+pub mod web_rtc;
+pub mod negotiation;
 mod event_bus;
+mod peer_agent;
+pub mod signaling;
+
+// Errors stay until dismissed, the user may have to act on them
+const TOAST_DURATION_MS: i32 = 6_000;
+const MAX_TOASTS: usize = 4;
 
 pub struct App {
     link: ComponentLink<Self>,
-    chat_messages: Vec<ChatMessage>,
-    web_rtc: Rc<RefCell<WebRTC>>,
+    peer: Box<dyn Bridge<PeerAgent>>,
+    // Never empty, closing the last conversation opens a new one
+    conversations: Vec<Conversation>,
+    active: ConversationId,
+    conversation_ref: NodeRef,
+    // The scroll position of the shown conversation is restored once rendered
+    restore_scroll: bool,
+    show_diagnostics: bool,
+    show_settings: bool,
+    settings: Settings,
+    contact_book: ContactBook,
+    route: Route,
+    toasts: Vec<Toast>,
+    next_toast_id: u32,
     _producer: Box<dyn Bridge<EventBus>>,
+    _on_hash_change: Closure<dyn FnMut(JsValue)>,
+}
+
+#[derive(Properties, Clone, Default)]
+pub struct AppProps {
+    /// Overrides the signaling chosen with the query string
+    #[prop_or_default]
+    pub signaling: Option<SignalingConfig>,
 }
 
 pub enum ActionMessage {
-    OnConnect(Participants),
-    HandleMessage(ChatMessage),
+    OnConnect(ConversationId, ConnectRequest),
+    OnDisconnect,
+    Connecting(ConversationId),
+    Disconnected(ConversationId),
+    HandleMessage(ConversationId, ChatMessage),
+    ConnectionStateChanged(ConversationId, ConnectionState),
+    SessionCode(ConversationId, String),
+    NewConversation,
+    SelectConversation(ConversationId),
+    CloseConversation(ConversationId),
+    SaveDraft(ConversationId, String),
+    AddContact(String),
+    RemoveContact(String),
+    ConnectContact(String),
+    ToggleDiagnostics,
+    ToggleSettings,
+    ToggleTheme,
+    SaveSettings(Settings),
+    ResetSettings,
+    Notify(Notification),
+    DismissToast(u32),
+    RouteChanged(Route),
+    Ignore,
+}
+
+impl App {
+    fn active(&self) -> &Conversation {
+        self.conversations
+            .iter()
+            .find(|conversation| conversation.id == self.active)
+            .unwrap_or(&self.conversations[0])
+    }
+
+    // `None` for the conversations of another `App`, they share the agent
+    fn conversation_mut(&mut self, id: ConversationId) -> Option<&mut Conversation> {
+        self.conversations.iter_mut().find(|conversation| conversation.id == id)
+    }
+
+    fn connect(&mut self, id: ConversationId, request: ConnectRequest) {
+        // The contacts are told when this name is online
+        let me = match &request.mode {
+            ConnectMode::Server(participants) | ConnectMode::Room { participants, .. } => Some(&participants.user_from),
+            _ => None,
+        };
+        if let Some(me) = me.filter(|me| self.contact_book.me.as_ref() != Some(*me)) {
+            self.contact_book.me = Some(me.clone());
+            self.contact_book.save();
+        }
+        let room = match self.conversation_mut(id) {
+            Some(conversation) => {
+                conversation.on_connect(&request);
+                conversation.room.clone()
+            }
+            None => return,
+        };
+        if let Some(room) = room.filter(|_| id == self.active) {
+            let route = Route::Room(room);
+            if route != self.route {
+                route.navigate();
+            }
+        }
+        self.peer.send(PeerRequest::Connect(id, request));
+    }
+
+    fn on_route_changed(&mut self, route: Route) {
+        // The link of a room another conversation is in
+        let other = route.room().and_then(|room| {
+            self.conversations
+                .iter()
+                .find(|conversation| conversation.id != self.active && conversation.room.as_deref() == Some(room))
+        });
+        if let Some(id) = other.map(|conversation| conversation.id) {
+            self.route = route;
+            self.select(id);
+            return;
+        }
+
+        let active = self.active;
+        let conversation = match self.conversation_mut(active) {
+            Some(conversation) => conversation,
+            None => return,
+        };
+        // Leaves the room of the previous page, with the back button for instance
+        if conversation.room.is_some() && conversation.room.as_deref() != route.room() {
+            conversation.room = None;
+            self.peer.send(PeerRequest::Disconnect(active));
+        }
+        if let (Route::Room(room), None) = (&route, &self.active().room) {
+            let last_room = self.active().last_room.clone().filter(|request| match &request.mode {
+                ConnectMode::Room { participants, .. } => participants.room.as_ref() == Some(room),
+                _ => false,
+            });
+            if let Some(request) = last_room {
+                self.route = route.clone();
+                self.connect(active, request);
+            }
+        }
+        self.route = route;
+    }
+
+    fn select(&mut self, id: ConversationId) {
+        let scroll_top = self.conversation_ref.cast::<Element>().map(|element| element.scroll_top());
+        let active = self.active;
+        if let (Some(conversation), Some(scroll_top)) = (self.conversation_mut(active), scroll_top) {
+            conversation.scroll_top = scroll_top;
+        }
+        self.active = id;
+        self.restore_scroll = true;
+        if let Some(conversation) = self.conversation_mut(id) {
+            conversation.unread = 0;
+        }
+        // Switching is not a step of the history, the back button leaves the page
+        let route = self.active().room.clone().map_or(Route::Home, Route::Room);
+        if route != self.route {
+            route.replace();
+            self.route = route;
+        }
+    }
+
+    // In the shown conversation, or in a new one when it is busy
+    fn connect_contact(&mut self, name: String) {
+        let me = match &self.contact_book.me {
+            Some(me) => me.clone(),
+            None => return,
+        };
+        if self.active().connected {
+            let conversation = Conversation::new();
+            let id = conversation.id;
+            self.conversations.push(conversation);
+            self.select(id);
+        }
+        let request = ConnectRequest {
+            mode: ConnectMode::Server(Participants {
+                user_from: me,
+                user_to: name,
+                room: None,
+            }),
+            candidate_policy: CandidatePolicy::default(),
+        };
+        self.connect(self.active, request);
+    }
+
+    fn close(&mut self, id: ConversationId) {
+        self.peer.send(PeerRequest::Close(id));
+        self.conversations.retain(|conversation| conversation.id != id);
+        if self.conversations.is_empty() {
+            self.conversations.push(Conversation::new());
+        }
+        if id == self.active {
+            self.select(self.conversations[0].id);
+        }
+    }
+
+    fn notify(&mut self, notification: Notification) {
+        let shown = match notification.severity {
+            Severity::Info => self.settings.notifications.info,
+            Severity::Warning => self.settings.notifications.warnings,
+            Severity::Error => true,
+        };
+        if !shown {
+            return;
+        }
+        let id = self.next_toast_id;
+        self.next_toast_id = self.next_toast_id.wrapping_add(1);
+        if notification.severity != Severity::Error {
+            let link = self.link.clone();
+            let dismiss = Closure::once_into_js(move || link.send_message(ActionMessage::DismissToast(id)));
+            if let Some(window) = web_sys::window() {
+                if let Err(err) = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                    dismiss.unchecked_ref(),
+                    TOAST_DURATION_MS,
+                ) {
+                    log::error!("Could not schedule the toast dismissal {:?}", err);
+                }
+            }
+        }
+        self.toasts.push(Toast { id, notification });
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+    }
+
+    // Whether the settings were saved, the user is told otherwise
+    fn save_settings(&mut self, settings: Settings) -> bool {
+        if let Err(err) = settings.save() {
+            self.notify(Notification::new(Severity::Error, err.user_message()));
+            return false;
+        }
+        theme::apply(settings.theme, settings.custom_theme.as_ref());
+        self.settings = settings;
+        true
+    }
+
+    fn view_sidebar(&self) -> Html {
+        let conversations = self
+            .conversations
+            .iter()
+            .map(|conversation| ConversationSummary {
+                id: conversation.id,
+                title: conversation.title().to_string(),
+                unread: conversation.unread,
+                connection_state: conversation.connection_state,
+            })
+            .collect::<Vec<ConversationSummary>>();
+        html! {
+            <components::sidebar::Sidebar
+                conversations=conversations
+                active=self.active
+                show_unread=self.settings.notifications.unread_badges
+                on_select=self.link.callback(ActionMessage::SelectConversation)
+                on_new=self.link.callback(|_: ()| ActionMessage::NewConversation)
+                on_close=self.link.callback(ActionMessage::CloseConversation)/>
+        }
+    }
+    fn view_connect(&self) -> Html {
+        if self.show_settings {
+            return html! {
+                <components::settings::SettingsPanel
+                    settings=self.settings.clone()
+                    on_save=self.link.callback(ActionMessage::SaveSettings)
+                    on_reset=self.link.callback(|_: ()| ActionMessage::ResetSettings)
+                    on_close=self.link.callback(|_: ()| ActionMessage::ToggleSettings)/>
+            };
+        }
+        let active = self.active();
+        let id = active.id;
+        html! {
+            <>
+                <components::connect::Connect
+                    on_connect=self.link.callback(move |request: ConnectRequest| ActionMessage::OnConnect(id, request))
+                    on_disconnect=self.link.callback(|_: ()| ActionMessage::OnDisconnect)
+                    conversation=id
+                    connected=active.connected
+                    session_code=active.session_code.clone()
+                    room=self.route.room().map(String::from)
+                    joined_room=active.room.clone()
+                    display_name=self.settings.display_name.clone()>
+                </components::connect::Connect>
+                {
+                    if self.show_diagnostics {
+                        html! { <components::diagnostics::Diagnostics conversation=id/> }
+                    } else {
+                        html! {}
+                    }
+                }
+            </>
+        }
+    }
 }
 
 impl Component for App {
     type Message = ActionMessage;
-    type Properties = ();
+    type Properties = AppProps;
 
     // https://doc.rust-lang.org/rust-by-example/trait.html
-    fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let web_rtc_manager = Rc::new(RefCell::new(web_rtc::WebRTC::new()));
-        let cloned_link = link.clone();
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        // "?signaling=broadcast" connects the tabs of this browser, without server
+        let query = web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .unwrap_or_default();
+        let mut peer = PeerAgent::bridge(link.callback(|response| match response {
+            PeerResponse::Connecting(id) => ActionMessage::Connecting(id),
+            PeerResponse::Disconnected(id) => ActionMessage::Disconnected(id),
+            PeerResponse::Message(id, chat_message) => ActionMessage::HandleMessage(id, chat_message),
+            PeerResponse::StateChanged(id, state) => ActionMessage::ConnectionStateChanged(id, state),
+            PeerResponse::SessionCode(id, code) => ActionMessage::SessionCode(id, code),
+            // Asked by the diagnostics
+            PeerResponse::Stats(..) => ActionMessage::Ignore,
+        }));
+        peer.send(PeerRequest::Configure(
+            props
+                .signaling
+                .unwrap_or_else(|| SignalingConfig::from_query(&query)),
+        ));
+        let mut conversations = vec![];
+        // "?rejoin" comes from the crash overlay, the rooms are in the crash report
+        if query_parameter(&query, "rejoin").is_some() {
+            for rejoin in crash::take_rooms() {
+                let conversation = Conversation::new();
+                let mode = match rejoin.participants.room {
+                    Some(_) => ConnectMode::Room {
+                        participants: rejoin.participants,
+                        host: rejoin.host,
+                    },
+                    None => ConnectMode::Server(rejoin.participants),
+                };
+                link.send_message(ActionMessage::OnConnect(
+                    conversation.id,
+                    ConnectRequest {
+                        mode,
+                        candidate_policy: rejoin.candidate_policy,
+                    },
+                ));
+                conversations.push(conversation);
+            }
+        }
+        if conversations.is_empty() {
+            conversations.push(Conversation::new());
+        }
+        let active = conversations[0].id;
+        // "?demo" starts a conversation with the echo bot right away
+        if query_parameter(&query, "demo").is_some() {
+            link.send_message(ActionMessage::OnConnect(
+                active,
+                ConnectRequest {
+                    mode: ConnectMode::Demo,
+                    candidate_policy: CandidatePolicy::All,
+                },
+            ));
+        }
+        let mut producer = EventBus::bridge(link.callback(|event| match event {
+            Event::Notification(notification) => ActionMessage::Notify(notification),
+            // Not subscribed
+            _ => ActionMessage::Ignore,
+        }));
+        producer.send(Request::Subscribe(vec![Topic::Notification]));
+        // "#/room/<id>" comes from an invite link
+        let on_route_changed = link.callback(ActionMessage::RouteChanged);
+        let on_hash_change: Closure<dyn FnMut(JsValue)> =
+            Closure::wrap(Box::new(move |_: JsValue| on_route_changed.emit(Route::current())));
+        if let Some(window) = web_sys::window() {
+            window.set_onhashchange(Some(on_hash_change.as_ref().unchecked_ref()));
+        }
+        // Those of a newer version of the application are kept until the user resets them
+        let settings = Settings::try_load().unwrap_or_else(|err| {
+            log::error!("Could not read the settings, using the default ones: {}", err);
+            link.send_message(ActionMessage::Notify(Notification::from(&err)));
+            Settings::default()
+        });
+        theme::apply(settings.theme, settings.custom_theme.as_ref());
         Self {
             link,
-            chat_messages: vec![],
-            web_rtc: web_rtc_manager,
-            _producer: EventBus::bridge(cloned_link.callback(ActionMessage::HandleMessage)),
+            peer,
+            conversations,
+            active,
+            conversation_ref: NodeRef::default(),
+            restore_scroll: false,
+            show_diagnostics: false,
+            show_settings: false,
+            settings,
+            contact_book: ContactBook::load(),
+            route: Route::current(),
+            toasts: vec![],
+            next_toast_id: 0,
+            _producer: producer,
+            _on_hash_change: on_hash_change,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        let active = self.active;
+        let history_limit = self.settings.history_limit;
         match msg {
-            ActionMessage::HandleMessage(chat_message) => {
-                if let SenderType::ME = chat_message.from {
-                    web_rtc::WebRTC::send_webrtc_message(self.web_rtc.clone(), &chat_message.content);
+            ActionMessage::HandleMessage(id, chat_message) => match self.conversation_mut(id) {
+                Some(conversation) => conversation.on_message(chat_message, id == active, history_limit),
+                None => return false,
+            },
+            ActionMessage::OnConnect(id, request) => {
+                self.connect(id, request);
+                return false;
+            }
+            ActionMessage::OnDisconnect => {
+                let left_room = self
+                    .conversation_mut(active)
+                    .is_some_and(|conversation| conversation.room.take().is_some());
+                if left_room {
+                    Route::Home.navigate();
+                }
+                self.peer.send(PeerRequest::Disconnect(active));
+                return false;
+            }
+            ActionMessage::Connecting(id) => match self.conversation_mut(id) {
+                Some(conversation) => conversation.connected = true,
+                None => return false,
+            },
+            ActionMessage::Disconnected(id) => match self.conversation_mut(id) {
+                Some(conversation) => conversation.on_disconnected(),
+                None => return false,
+            },
+            ActionMessage::ConnectionStateChanged(id, state) => match self.conversation_mut(id) {
+                Some(conversation) => conversation.on_connection_state_changed(state),
+                None => return false,
+            },
+            ActionMessage::SessionCode(id, code) => match self.conversation_mut(id) {
+                Some(conversation) => conversation.session_code = Some(code),
+                None => return false,
+            },
+            ActionMessage::NewConversation => {
+                let conversation = Conversation::new();
+                let id = conversation.id;
+                self.conversations.push(conversation);
+                self.select(id);
+            }
+            ActionMessage::SelectConversation(id) => {
+                if id == active {
+                    return false;
                 }
-                self.chat_messages.push(chat_message);
+                self.select(id);
             }
-            ActionMessage::OnConnect(from_to) => {
-                web_rtc::WebRTC::connect(self.web_rtc.clone(), from_to)
+            ActionMessage::CloseConversation(id) => {
+                self.close(id);
             }
+            ActionMessage::SaveDraft(id, draft) => {
+                if let Some(conversation) = self.conversation_mut(id) {
+                    conversation.draft = draft;
+                }
+                return false;
+            }
+            ActionMessage::AddContact(name) => {
+                if !self.contact_book.add(&name) {
+                    return false;
+                }
+                self.contact_book.save();
+            }
+            ActionMessage::RemoveContact(name) => {
+                self.contact_book.remove(&name);
+                self.contact_book.save();
+            }
+            ActionMessage::ConnectContact(name) => {
+                self.connect_contact(name);
+            }
+            ActionMessage::ToggleDiagnostics => {
+                self.show_diagnostics = !self.show_diagnostics;
+            }
+            ActionMessage::ToggleSettings => {
+                self.show_settings = !self.show_settings;
+            }
+            ActionMessage::ToggleTheme => {
+                let theme = self
+                    .settings
+                    .theme
+                    .toggled(self.settings.custom_theme.as_ref(), theme::system_prefers_dark());
+                let settings = Settings {
+                    theme,
+                    ..self.settings.clone()
+                };
+                self.save_settings(settings);
+            }
+            ActionMessage::SaveSettings(settings) => {
+                if self.save_settings(settings) {
+                    self.show_settings = false;
+                }
+            }
+            ActionMessage::ResetSettings => match Settings::reset() {
+                Ok(settings) => {
+                    theme::apply(settings.theme, settings.custom_theme.as_ref());
+                    self.settings = settings;
+                }
+                Err(err) => self.notify(Notification::from(&err)),
+            },
+            ActionMessage::Notify(notification) => {
+                self.notify(notification);
+            }
+            ActionMessage::DismissToast(id) => {
+                self.toasts.retain(|toast| toast.id != id);
+            }
+            ActionMessage::RouteChanged(route) => {
+                self.on_route_changed(route);
+            }
+            ActionMessage::Ignore => return false,
         };
         true
     }
@@ -64,18 +536,65 @@ impl Component for App {
         false
     }
 
+    fn rendered(&mut self, _first_render: bool) {
+        if self.restore_scroll {
+            self.restore_scroll = false;
+            if let Some(element) = self.conversation_ref.cast::<Element>() {
+                element.set_scroll_top(self.active().scroll_top);
+            }
+        }
+    }
+
+    fn destroy(&mut self) {
+        if let Some(window) = web_sys::window() {
+            window.set_onhashchange(None);
+        }
+    }
+
     fn view(&self) -> Html {
+        let active = self.active();
+        let id = active.id;
         html! {
             <>
+                <components::toast::Toasts
+                    toasts=self.toasts.clone()
+                    on_dismiss=self.link.callback(ActionMessage::DismissToast)/>
                 <section class="app">
+                    <aside class="app__side">
+                        { self.view_sidebar() }
+                        <components::contacts::Contacts
+                            contact_book=self.contact_book.clone()
+                            on_add=self.link.callback(ActionMessage::AddContact)
+                            on_remove=self.link.callback(ActionMessage::RemoveContact)
+                            on_connect=self.link.callback(ActionMessage::ConnectContact)/>
+                        <div class="app__side-buttons">
+                            <button id="open-settings" class="app__side-button" onclick=self.link.callback(|_| ActionMessage::ToggleSettings)>
+                                <i class="material-icons">{"settings"}</i>{"Settings"}
+                            </button>
+                            <button id="toggle-theme" class="app__side-button" title="Switch between the light and dark themes"
+                                onclick=self.link.callback(|_| ActionMessage::ToggleTheme)>
+                                <i class="material-icons">{"brightness_6"}</i>{"Theme"}
+                            </button>
+                        </div>
+                    </aside>
                     <section class="app__connect">
-                        <components::connect::Connect on_connect=self.link.callback(|fromTo: Participants| ActionMessage::OnConnect(fromTo))></components::connect::Connect>
+                        { self.view_connect() }
                     </section>
                     <section class="app__chat">
-                        <section class="conversation-container">
-                            { self.chat_messages.iter().map(|message| message.view()).collect::<Html>() }
+                        <section class=format!("app__status status--{}", active.connection_state)>
+                            { active.connection_state.label() }
+                            <button class="app__status-button" onclick=self.link.callback(|_| ActionMessage::ToggleDiagnostics)>
+                                { if self.show_diagnostics { "Hide diagnostics" } else { "Diagnostics" } }
+                            </button>
+                        </section>
+                        <section class="conversation-container" ref=self.conversation_ref.clone()>
+                            { active.chat_messages.iter().map(|message| message.view()).collect::<Html>() }
                         </section>
-                        <components::chatbox::ChatBox/>
+                        <components::chatbox::ChatBox
+                            conversation=id
+                            draft=active.draft.clone()
+                            send_key=self.settings.send_key
+                            on_draft=self.link.callback(|(id, draft): (ConversationId, String)| ActionMessage::SaveDraft(id, draft))/>
                     </section>
                 </section>
             </>
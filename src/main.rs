@@ -1,16 +1,6 @@
 fn main() {
     wasm_logger::init(wasm_logger::Config::default());
+    // Before anything else, so no panic ends up in a frozen page
+    prust::crash::install_panic_hook();
     yew::start_app::<prust::App>();
 }
-
-#[allow(dead_code)]
-pub fn set_panic_hook() {
-    // When the `console_error_panic_hook` feature is enabled, we can call the
-    // `set_panic_hook` function at least once during initialization, and then
-    // we will get better error messages if our code ever panics.
-    //
-    // For more details see
-    // https://github.com/rustwasm/console_error_panic_hook#readme
-    #[cfg(feature = "console_error_panic_hook")]
-    console_error_panic_hook::set_once();
-}
@@ -0,0 +1,435 @@
+use std::collections::HashSet;
+
+use crate::utils::socket::{Candidate, SDPMessage, SdpType, SignalingMessage};
+
+// createDataChannel throws, and the wasm module aborts, outside of these limits
+// https://www.w3.org/TR/webrtc/#dom-rtcpeerconnection-createdatachannel
+const MAX_DATA_CHANNEL_ID: u16 = 65534;
+const MAX_DATA_CHANNEL_LABEL_BYTES: usize = 65535;
+
+/// What happened, on the signaling side or in the peer connection
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// The server put us in a room
+    JoinedRoom(String),
+    /// The other peer is in the room, with the id of the data channel to open
+    PeerHere(u16),
+    PeerLeft,
+    /// The peer connection fired negotiationneeded
+    NegotiationNeeded,
+    RestartIce,
+    /// A description created after `CreateOffer` or `CreateAnswer` is now the local one
+    LocalDescriptionSet(SDPMessage),
+    LocalDescriptionFailed,
+    /// The description of `SetRemoteDescription` is now the remote one
+    RemoteDescriptionSet,
+    RemoteDescriptionFailed,
+    /// With its order among the descriptions of the other peer, 0 when unknown
+    RemoteDescription(SDPMessage, u32),
+    RemoteCandidate(Candidate),
+    LocalCandidate(Candidate),
+    ChannelOpen,
+}
+
+/// What the peer connection or the signaling must do
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    OpenDataChannel { label: String, id: u16 },
+    /// Creates an offer and sets it as local description, then reports `LocalDescriptionSet`
+    CreateOffer { ice_restart: bool },
+    /// Creates an answer and sets it as local description, then reports `LocalDescriptionSet`
+    CreateAnswer,
+    /// Reports `RemoteDescriptionSet` once done. An offer received with a pending local offer
+    /// rolls it back implicitly.
+    SetRemoteDescription(SDPMessage),
+    AddIceCandidate(Candidate),
+    Send(SignalingMessage),
+    Close,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SignalingState {
+    Stable,
+    HaveLocalOffer,
+    HaveRemoteOffer,
+}
+
+/// Decisions of the offer/answer exchange, without any browser API so they can be simulated.
+///
+/// Glare, both peers sending an offer at the same time, is solved as in the "perfect negotiation"
+/// pattern: the polite peer gives its offer up for the other one, the impolite peer ignores the
+/// offer of the polite one. Both peers must agree on who is polite.
+pub struct Negotiation {
+    polite: bool,
+    room: Option<String>,
+    channel_opened: bool,
+    signaling_state: SignalingState,
+    making_offer: bool,
+    // Type of the remote description being set
+    pending_remote: Option<SdpType>,
+    remote_description_set: bool,
+    candidates_buffer: Vec<Candidate>,
+    // The signaling may deliver a message twice
+    seen_candidates: HashSet<(String, String)>,
+    seen_descriptions: HashSet<String>,
+    // The signaling may also deliver an offer given up after the answer which replaced it
+    descriptions_sent: u32,
+    last_remote_order: u32,
+    is_offerer: bool,
+    connected: bool,
+}
+
+impl Negotiation {
+    pub fn new(polite: bool) -> Self {
+        Self {
+            polite,
+            room: None,
+            channel_opened: false,
+            signaling_state: SignalingState::Stable,
+            making_offer: false,
+            pending_remote: None,
+            remote_description_set: false,
+            candidates_buffer: vec![],
+            seen_candidates: HashSet::new(),
+            seen_descriptions: HashSet::new(),
+            descriptions_sent: 0,
+            last_remote_order: 0,
+            is_offerer: false,
+            connected: false,
+        }
+    }
+
+    /// The peer with the greatest name is polite, both peers know both names
+    pub fn is_polite(user_from: &str, user_to: &str) -> bool {
+        user_from > user_to
+    }
+
+    pub fn room(&self) -> Option<&str> {
+        self.room.as_deref()
+    }
+
+    /// Whether the last offer accepted came from us, only this peer restarts ICE
+    pub fn is_offerer(&self) -> bool {
+        self.is_offerer
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn signaling_state(&self) -> SignalingState {
+        self.signaling_state
+    }
+
+    pub fn handle(&mut self, event: Event) -> Vec<Command> {
+        match event {
+            Event::JoinedRoom(room) => {
+                self.room = Some(room);
+                vec![]
+            }
+            Event::PeerHere(id) if id > MAX_DATA_CHANNEL_ID => {
+                log::warn!("Negotiation: ignoring invalid data channel id {}", id);
+                vec![]
+            }
+            Event::PeerHere(id) => match &self.room {
+                Some(room) if room.len() > MAX_DATA_CHANNEL_LABEL_BYTES => {
+                    log::warn!("Negotiation: the room name is too long for a data channel label");
+                    vec![]
+                }
+                Some(room) if !self.channel_opened => {
+                    self.channel_opened = true;
+                    vec![Command::OpenDataChannel { label: room.clone(), id }]
+                }
+                _ => vec![],
+            },
+            Event::PeerLeft => vec![Command::Close],
+            Event::NegotiationNeeded => {
+                // Fired again by the browser once stable, if still needed
+                if self.making_offer || self.is_busy() {
+                    return vec![];
+                }
+                self.create_offer(false)
+            }
+            Event::RestartIce => {
+                if !self.is_offerer || self.making_offer || self.signaling_state == SignalingState::HaveRemoteOffer {
+                    return vec![];
+                }
+                // The candidates of the new ICE generation wait for the answer to the restart
+                self.remote_description_set = false;
+                self.create_offer(true)
+            }
+            Event::LocalDescriptionSet(description) => self.on_local_description(description),
+            Event::LocalDescriptionFailed => {
+                // Most likely an offer rolled back by the one of the other peer
+                self.making_offer = false;
+                vec![]
+            }
+            Event::RemoteDescription(description, order) => self.on_remote_description(description, order),
+            Event::RemoteDescriptionSet => self.on_remote_description_set(),
+            Event::RemoteDescriptionFailed => {
+                self.pending_remote = None;
+                vec![]
+            }
+            Event::RemoteCandidate(candidate) => self.on_remote_candidate(candidate),
+            Event::LocalCandidate(candidate) => {
+                vec![Command::Send(SignalingMessage::ICECandidate { message: candidate })]
+            }
+            Event::ChannelOpen => {
+                self.connected = true;
+                vec![]
+            }
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        self.signaling_state != SignalingState::Stable || self.pending_remote.is_some()
+    }
+
+    fn create_offer(&mut self, ice_restart: bool) -> Vec<Command> {
+        self.making_offer = true;
+        vec![Command::CreateOffer { ice_restart }]
+    }
+
+    fn on_local_description(&mut self, description: SDPMessage) -> Vec<Command> {
+        match description.type_ {
+            SdpType::Offer => {
+                self.making_offer = false;
+                self.is_offerer = true;
+                self.signaling_state = SignalingState::HaveLocalOffer;
+            }
+            SdpType::Answer | SdpType::Pranswer => {
+                self.is_offerer = false;
+                self.signaling_state = SignalingState::Stable;
+            }
+            SdpType::Rollback => {
+                self.signaling_state = SignalingState::Stable;
+                return vec![];
+            }
+        }
+        self.descriptions_sent += 1;
+        vec![Command::Send(SignalingMessage::SDP {
+            message: description,
+            order: self.descriptions_sent,
+        })]
+    }
+
+    fn on_remote_description(&mut self, description: SDPMessage, order: u32) -> Vec<Command> {
+        if !self.seen_descriptions.insert(description.sdp.clone()) {
+            return vec![];
+        }
+        if order != 0 && order <= self.last_remote_order {
+            log::info!("Negotiation: ignoring a description older than the last one accepted");
+            return vec![];
+        }
+        let commands = self.accept_remote_description(description);
+        if order != 0 && !commands.is_empty() {
+            self.last_remote_order = order;
+        }
+        commands
+    }
+
+    fn accept_remote_description(&mut self, description: SDPMessage) -> Vec<Command> {
+        match description.type_ {
+            SdpType::Offer => {
+                let collision = self.making_offer || self.is_busy();
+                if collision && !self.polite {
+                    log::info!("Negotiation: ignoring the offer of the polite peer");
+                    return vec![];
+                }
+                // The polite peer gives its own offer up
+                self.making_offer = false;
+                self.pending_remote = Some(SdpType::Offer);
+                vec![Command::SetRemoteDescription(description)]
+            }
+            SdpType::Answer => {
+                if self.signaling_state != SignalingState::HaveLocalOffer || self.pending_remote.is_some() {
+                    log::info!("Negotiation: ignoring an answer to no pending offer");
+                    return vec![];
+                }
+                self.pending_remote = Some(SdpType::Answer);
+                vec![Command::SetRemoteDescription(description)]
+            }
+            SdpType::Pranswer | SdpType::Rollback => {
+                log::warn!("Negotiation: ignoring unsupported {:?}", description.type_);
+                vec![]
+            }
+        }
+    }
+
+    fn on_remote_description_set(&mut self) -> Vec<Command> {
+        let pending_remote = match self.pending_remote.take() {
+            Some(pending_remote) => pending_remote,
+            None => return vec![],
+        };
+        self.remote_description_set = true;
+        // Buffered candidates only belong to this negotiation
+        let mut commands: Vec<Command> = self
+            .candidates_buffer
+            .drain(..)
+            .map(Command::AddIceCandidate)
+            .collect();
+        if pending_remote == SdpType::Offer {
+            self.signaling_state = SignalingState::HaveRemoteOffer;
+            commands.push(Command::CreateAnswer);
+        } else {
+            self.signaling_state = SignalingState::Stable;
+        }
+        commands
+    }
+
+    fn on_remote_candidate(&mut self, candidate: Candidate) -> Vec<Command> {
+        let key = (candidate.sdp_mid.clone(), candidate.candidate.clone());
+        if !self.seen_candidates.insert(key) {
+            return vec![];
+        }
+        if !self.remote_description_set || self.pending_remote.is_some() {
+            self.candidates_buffer.push(candidate);
+            return vec![];
+        }
+        vec![Command::AddIceCandidate(candidate)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn description(type_: SdpType, sdp: &str) -> SDPMessage {
+        SDPMessage {
+            type_,
+            sdp: sdp.to_string(),
+        }
+    }
+
+    fn candidate(candidate: &str) -> Candidate {
+        Candidate {
+            candidate: candidate.to_string(),
+            sdp_mid: String::from("0"),
+            sdp_m_line_index: 0,
+        }
+    }
+
+    #[test]
+    fn opens_the_data_channel_once() {
+        let mut negotiation = Negotiation::new(false);
+        assert!(negotiation.handle(Event::PeerHere(0)).is_empty());
+        negotiation.handle(Event::JoinedRoom(String::from("room")));
+        assert_eq!(
+            negotiation.handle(Event::PeerHere(0)),
+            vec![Command::OpenDataChannel { label: String::from("room"), id: 0 }]
+        );
+        assert!(negotiation.handle(Event::PeerHere(0)).is_empty());
+    }
+
+    #[test]
+    fn rejects_data_channels_the_browser_would_refuse() {
+        let mut negotiation = Negotiation::new(false);
+        negotiation.handle(Event::JoinedRoom(String::from("room")));
+        assert!(negotiation.handle(Event::PeerHere(u16::MAX)).is_empty());
+
+        let mut negotiation = Negotiation::new(false);
+        negotiation.handle(Event::JoinedRoom("a".repeat(MAX_DATA_CHANNEL_LABEL_BYTES + 1)));
+        assert!(negotiation.handle(Event::PeerHere(0)).is_empty());
+    }
+
+    #[test]
+    fn buffers_candidates_until_the_remote_description_is_set() {
+        let mut negotiation = Negotiation::new(true);
+        assert!(negotiation.handle(Event::RemoteCandidate(candidate("a"))).is_empty());
+        assert_eq!(
+            negotiation.handle(Event::RemoteDescription(description(SdpType::Offer, "offer"), 0)),
+            vec![Command::SetRemoteDescription(description(SdpType::Offer, "offer"))]
+        );
+        assert!(negotiation.handle(Event::RemoteCandidate(candidate("b"))).is_empty());
+        assert_eq!(
+            negotiation.handle(Event::RemoteDescriptionSet),
+            vec![
+                Command::AddIceCandidate(candidate("a")),
+                Command::AddIceCandidate(candidate("b")),
+                Command::CreateAnswer,
+            ]
+        );
+        assert_eq!(
+            negotiation.handle(Event::RemoteCandidate(candidate("c"))),
+            vec![Command::AddIceCandidate(candidate("c"))]
+        );
+    }
+
+    #[test]
+    fn ignores_duplicates() {
+        let mut negotiation = Negotiation::new(true);
+        negotiation.handle(Event::RemoteDescription(description(SdpType::Offer, "offer"), 0));
+        assert!(negotiation.handle(Event::RemoteDescription(description(SdpType::Offer, "offer"), 0)).is_empty());
+        negotiation.handle(Event::RemoteCandidate(candidate("a")));
+        negotiation.handle(Event::RemoteCandidate(candidate("a")));
+        assert_eq!(negotiation.handle(Event::RemoteDescriptionSet).len(), 2);
+    }
+
+    #[test]
+    fn the_impolite_peer_ignores_a_colliding_offer() {
+        let mut negotiation = Negotiation::new(false);
+        assert_eq!(
+            negotiation.handle(Event::NegotiationNeeded),
+            vec![Command::CreateOffer { ice_restart: false }]
+        );
+        assert!(negotiation.handle(Event::RemoteDescription(description(SdpType::Offer, "offer"), 0)).is_empty());
+    }
+
+    #[test]
+    fn the_polite_peer_gives_its_offer_up() {
+        let mut negotiation = Negotiation::new(true);
+        negotiation.handle(Event::NegotiationNeeded);
+        negotiation.handle(Event::LocalDescriptionSet(description(SdpType::Offer, "mine")));
+        assert_eq!(
+            negotiation.handle(Event::RemoteDescription(description(SdpType::Offer, "theirs"), 0)),
+            vec![Command::SetRemoteDescription(description(SdpType::Offer, "theirs"))]
+        );
+        negotiation.handle(Event::RemoteDescriptionSet);
+        assert_eq!(negotiation.signaling_state(), SignalingState::HaveRemoteOffer);
+        // The answer to the offer given up never comes
+        assert!(negotiation.handle(Event::RemoteDescription(description(SdpType::Answer, "late"), 0)).is_empty());
+    }
+
+    #[test]
+    fn only_the_offerer_restarts_ice() {
+        let mut answerer = Negotiation::new(true);
+        answerer.handle(Event::RemoteDescription(description(SdpType::Offer, "offer"), 0));
+        answerer.handle(Event::RemoteDescriptionSet);
+        answerer.handle(Event::LocalDescriptionSet(description(SdpType::Answer, "answer")));
+        assert!(answerer.handle(Event::RestartIce).is_empty());
+
+        let mut offerer = Negotiation::new(false);
+        offerer.handle(Event::NegotiationNeeded);
+        offerer.handle(Event::LocalDescriptionSet(description(SdpType::Offer, "offer")));
+        offerer.handle(Event::RemoteDescription(description(SdpType::Answer, "answer"), 0));
+        offerer.handle(Event::RemoteDescriptionSet);
+        assert_eq!(offerer.handle(Event::RestartIce), vec![Command::CreateOffer { ice_restart: true }]);
+    }
+
+    #[test]
+    fn ignores_an_offer_given_up_delivered_after_its_answer() {
+        let mut impolite = Negotiation::new(false);
+        impolite.handle(Event::NegotiationNeeded);
+        impolite.handle(Event::LocalDescriptionSet(description(SdpType::Offer, "offer")));
+        impolite.handle(Event::RemoteDescription(description(SdpType::Answer, "answer"), 2));
+        impolite.handle(Event::RemoteDescriptionSet);
+        assert!(impolite.handle(Event::RemoteDescription(description(SdpType::Offer, "given up"), 1)).is_empty());
+    }
+
+    #[test]
+    fn buffers_the_candidates_of_an_ice_restart_until_its_answer() {
+        let mut offerer = Negotiation::new(false);
+        offerer.handle(Event::NegotiationNeeded);
+        offerer.handle(Event::LocalDescriptionSet(description(SdpType::Offer, "offer")));
+        offerer.handle(Event::RemoteDescription(description(SdpType::Answer, "answer"), 0));
+        offerer.handle(Event::RemoteDescriptionSet);
+        offerer.handle(Event::RestartIce);
+        offerer.handle(Event::LocalDescriptionSet(description(SdpType::Offer, "restart")));
+        assert!(offerer.handle(Event::RemoteCandidate(candidate("new"))).is_empty());
+        offerer.handle(Event::RemoteDescription(description(SdpType::Answer, "restart answer"), 0));
+        assert_eq!(
+            offerer.handle(Event::RemoteDescriptionSet),
+            vec![Command::AddIceCandidate(candidate("new"))]
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use yew::agent::{Dispatched, Dispatcher};
+use yew::worker::*;
+
+use crate::components::chat_message::{ChatMessage, SenderType};
+use crate::components::connect::{ConnectMode, ConnectRequest};
+use crate::components::toast::Notification;
+use crate::crash::{self, Rejoin};
+use crate::demo::DemoPeer;
+use crate::event_bus::{self, EventBus};
+use crate::signaling::{SignalingConfig, SignalingTransport, TransportState};
+use crate::utils::connection_state::ConnectionState;
+use crate::utils::error::CustomError;
+use crate::utils::socket::{PresenceSubscription, SocketMessage};
+use crate::utils::stats::ConnectionStats;
+use crate::web_rtc::{WebRTC, WebRTCConfig};
+
+/// Identifies a conversation of the sidebar, each one has its own peer session
+pub type ConversationId = u32;
+
+thread_local! {
+    static NEXT_CONVERSATION_ID: Cell<ConversationId> = const { Cell::new(0) };
+}
+
+/// Unique in the page: all the components share the agent
+pub fn new_conversation_id() -> ConversationId {
+    NEXT_CONVERSATION_ID.with(|next| {
+        let id = next.get();
+        next.set(id.wrapping_add(1));
+        id
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum PeerRequest {
+    /// Replaces the signaling, all the conversations are left
+    Configure(SignalingConfig),
+    Connect(ConversationId, ConnectRequest),
+    Send(ConversationId, String),
+    Disconnect(ConversationId),
+    /// Disconnects and forgets the session of the conversation
+    Close(ConversationId),
+    /// Answered with `Stats` to the requester only
+    GetStats(ConversationId),
+    /// The contacts coming and going are published on the `Presence` topic of the `EventBus`
+    WatchPresence(PresenceSubscription),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum PeerResponse {
+    /// The connection is being set up, the state changes follow
+    Connecting(ConversationId),
+    Disconnected(ConversationId),
+    Message(ConversationId, ChatMessage),
+    StateChanged(ConversationId, ConnectionState),
+    SessionCode(ConversationId, String),
+    Stats(ConversationId, Box<ConnectionStats>),
+}
+
+pub enum Msg {
+    Event(ConversationId, event_bus::Event),
+    SessionCode(ConversationId, String),
+    Stats(HandlerId, ConversationId, Box<ConnectionStats>),
+    Presence(SocketMessage),
+    PresenceTransport(TransportState),
+}
+
+struct Session {
+    web_rtc: Rc<RefCell<WebRTC>>,
+    demo_peer: Option<DemoPeer>,
+}
+
+/// Owns the signaling and a peer connection per conversation.
+/// The components only talk to it through messages, they never borrow `WebRTC` themselves.
+pub struct PeerAgent {
+    link: AgentLink<PeerAgent>,
+    subscribers: HashSet<HandlerId>,
+    signaling: SignalingConfig,
+    // Created on the first connection of the conversation, with its signaling transport
+    sessions: HashMap<ConversationId, Session>,
+    // Stays connected to the signaling server, whatever the conversations do
+    presence: Option<Box<dyn SignalingTransport>>,
+    watched: Option<PresenceSubscription>,
+    event_bus: Dispatcher<EventBus>,
+}
+
+impl PeerAgent {
+    fn web_rtc(&mut self, conversation: ConversationId) -> Rc<RefCell<WebRTC>> {
+        if let Some(session) = self.sessions.get(&conversation) {
+            return session.web_rtc.clone();
+        }
+        let web_rtc = Rc::new(RefCell::new(WebRTC::new(WebRTCConfig {
+            conversation,
+            signaling: self.signaling.clone(),
+            on_event: Some(self.link.callback(move |event| Msg::Event(conversation, event))),
+            ..WebRTCConfig::default()
+        })));
+        self.sessions.insert(
+            conversation,
+            Session {
+                web_rtc: web_rtc.clone(),
+                demo_peer: None,
+            },
+        );
+        web_rtc
+    }
+
+    fn connect(&mut self, conversation: ConversationId, request: ConnectRequest) -> Result<(), CustomError> {
+        let web_rtc = self.web_rtc(conversation);
+        let on_session_code = self.link.callback(move |code| Msg::SessionCode(conversation, code));
+        match request.mode {
+            ConnectMode::Server(participants) => {
+                WebRTC::connect(web_rtc, participants.clone(), request.candidate_policy)?;
+                crash::remember_room(
+                    conversation,
+                    Some(Rejoin {
+                        participants,
+                        candidate_policy: request.candidate_policy,
+                        host: false,
+                    }),
+                );
+            }
+            ConnectMode::Room { participants, host } => {
+                WebRTC::join_room(web_rtc, participants.clone(), request.candidate_policy, host)?;
+                crash::remember_room(
+                    conversation,
+                    Some(Rejoin {
+                        participants,
+                        candidate_policy: request.candidate_policy,
+                        host,
+                    }),
+                );
+            }
+            ConnectMode::CreateInvitation => {
+                WebRTC::create_manual_offer(web_rtc, request.candidate_policy, on_session_code)?;
+            }
+            ConnectMode::AcceptInvitation(code) => {
+                WebRTC::accept_manual_offer(web_rtc, &code, request.candidate_policy, on_session_code)?;
+            }
+            ConnectMode::CompleteInvitation(code) => {
+                WebRTC::accept_manual_answer(web_rtc, &code)?;
+            }
+            ConnectMode::Demo => {
+                let demo_peer = DemoPeer::connect(web_rtc)?;
+                if let Some(session) = self.sessions.get_mut(&conversation) {
+                    session.demo_peer = Some(demo_peer);
+                }
+            }
+        };
+        Ok(())
+    }
+
+    fn disconnect(&mut self, conversation: ConversationId) {
+        if let Some(session) = self.sessions.get_mut(&conversation) {
+            WebRTC::disconnect(session.web_rtc.clone());
+            session.demo_peer = None;
+        }
+        crash::remember_room(conversation, None);
+    }
+
+    fn watch_presence(&mut self) {
+        let subscription = match &self.watched {
+            Some(subscription) => subscription.clone(),
+            None => return,
+        };
+        if self.presence.is_none() {
+            match self.signaling.connect() {
+                Ok(presence) => {
+                    presence.subscribe(Some(self.link.callback(Msg::Presence)));
+                    presence.subscribe_state(Some(self.link.callback(Msg::PresenceTransport)));
+                    self.presence = Some(presence);
+                }
+                Err(err) => {
+                    log::error!("Could not watch the contacts: {}", err);
+                    return;
+                }
+            }
+        }
+        // Sent once open otherwise
+        if let Some(presence) = self.presence.as_ref().filter(|presence| presence.state() == TransportState::Open) {
+            presence.send(&SocketMessage::PresenceSubscribe { content: subscription });
+        }
+    }
+
+    fn broadcast(&self, response: PeerResponse) {
+        for sub in self.subscribers.iter() {
+            self.link.respond(*sub, response.clone());
+        }
+    }
+}
+
+impl Agent for PeerAgent {
+    type Reach = Context;
+    type Message = Msg;
+    type Input = PeerRequest;
+    type Output = PeerResponse;
+
+    fn create(link: AgentLink<Self>) -> Self {
+        // "?signaling=broadcast" connects the tabs of this browser, without server
+        let query = web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .unwrap_or_default();
+        Self {
+            link,
+            subscribers: HashSet::new(),
+            signaling: SignalingConfig::from_query(&query),
+            sessions: HashMap::new(),
+            presence: None,
+            watched: None,
+            event_bus: EventBus::dispatcher(),
+        }
+    }
+
+    // Called from the callbacks of `WebRTC`, which may still be borrowed: it must not be touched here
+    fn update(&mut self, msg: Self::Message) {
+        match msg {
+            Msg::Event(conversation, event_bus::Event::Chat(chat_message)) => {
+                self.broadcast(PeerResponse::Message(conversation, chat_message))
+            }
+            Msg::Event(conversation, event_bus::Event::ConnectionState(_, state)) => {
+                // Delivered late, after a newer connection replaced the closed one for instance
+                let replaced = match self.sessions.get(&conversation) {
+                    Some(session) => session
+                        .web_rtc
+                        .try_borrow()
+                        .is_ok_and(|web_rtc| web_rtc.state() != state),
+                    None => true,
+                };
+                if replaced {
+                    return;
+                }
+                crash::remember_state(conversation, Some(state));
+                if state == ConnectionState::Closed {
+                    // The other peer left
+                    crash::remember_room(conversation, None);
+                    if let Some(session) = self.sessions.get_mut(&conversation) {
+                        session.demo_peer = None;
+                    }
+                }
+                self.broadcast(PeerResponse::StateChanged(conversation, state));
+                self.event_bus.send(event_bus::Request::Publish(event_bus::Event::ConnectionState(
+                    conversation,
+                    state,
+                )));
+            }
+            Msg::Event(_, event) => self.event_bus.send(event_bus::Request::Publish(event)),
+            Msg::SessionCode(conversation, code) => self.broadcast(PeerResponse::SessionCode(conversation, code)),
+            Msg::Stats(id, conversation, stats) => self.link.respond(id, PeerResponse::Stats(conversation, stats)),
+            Msg::Presence(SocketMessage::PresenceNotify { content }) => {
+                let update = event_bus::PresenceUpdate {
+                    user: content.user,
+                    online: content.online,
+                };
+                self.event_bus.send(event_bus::Request::Publish(event_bus::Event::Presence(update)));
+            }
+            Msg::Presence(_) => {}
+            Msg::PresenceTransport(TransportState::Open) => self.watch_presence(),
+            Msg::PresenceTransport(TransportState::Closed) => {
+                log::error!("Lost the connection with the signaling server, the contacts are not watched anymore");
+                self.presence = None;
+            }
+            Msg::PresenceTransport(TransportState::Connecting) => {}
+        }
+    }
+
+    fn connected(&mut self, id: HandlerId) {
+        if id.is_respondable() {
+            self.subscribers.insert(id);
+        }
+    }
+
+    fn handle_input(&mut self, msg: Self::Input, id: HandlerId) {
+        match msg {
+            PeerRequest::Configure(signaling) => {
+                if signaling != self.signaling {
+                    let conversations: Vec<ConversationId> = self.sessions.keys().cloned().collect();
+                    for conversation in conversations {
+                        self.disconnect(conversation);
+                        crash::remember_state(conversation, None);
+                        self.broadcast(PeerResponse::Disconnected(conversation));
+                    }
+                    self.sessions.clear();
+                    self.presence = None;
+                    self.signaling = signaling;
+                    self.watch_presence();
+                }
+            }
+            PeerRequest::Connect(conversation, request) => match self.connect(conversation, request) {
+                Ok(()) => self.broadcast(PeerResponse::Connecting(conversation)),
+                Err(error) => {
+                    log::error!("Unable to connect: {}", error);
+                    let notification = Notification::from(&error);
+                    self.event_bus.send(event_bus::Request::Publish(event_bus::Event::Notification(notification)));
+                }
+            },
+            PeerRequest::Send(conversation, content) => {
+                let sent = match self.sessions.get(&conversation) {
+                    Some(session) => WebRTC::send_webrtc_message(session.web_rtc.clone(), &content),
+                    None => Err(CustomError::DataChannelError(String::from("The conversation is not connected"))),
+                };
+                match sent {
+                    // Only shown once it left, the friend would not see it otherwise
+                    Ok(()) => self.broadcast(PeerResponse::Message(conversation, ChatMessage::new(SenderType::ME, content))),
+                    Err(error) => {
+                        log::error!("Unable to send the message: {}", error);
+                        let notification = Notification::from(&error);
+                        self.event_bus.send(event_bus::Request::Publish(event_bus::Event::Notification(notification)));
+                    }
+                }
+            }
+            PeerRequest::Disconnect(conversation) => {
+                self.disconnect(conversation);
+                self.broadcast(PeerResponse::Disconnected(conversation));
+            }
+            PeerRequest::Close(conversation) => {
+                self.disconnect(conversation);
+                self.sessions.remove(&conversation);
+                crash::remember_state(conversation, None);
+            }
+            PeerRequest::WatchPresence(subscription) => {
+                self.watched = Some(subscription);
+                self.watch_presence();
+            }
+            PeerRequest::GetStats(conversation) => {
+                if let Some(session) = self.sessions.get(&conversation) {
+                    let on_stats = self.link.callback(move |stats| Msg::Stats(id, conversation, Box::new(stats)));
+                    WebRTC::get_stats(session.web_rtc.clone(), on_stats);
+                }
+            }
+        }
+    }
+
+    fn disconnected(&mut self, id: HandlerId) {
+        self.subscribers.remove(&id);
+    }
+
+    fn destroy(&mut self) {
+        let conversations: Vec<ConversationId> = self.sessions.keys().cloned().collect();
+        for conversation in conversations {
+            self.disconnect(conversation);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::{cell::RefCell, rc::Rc};
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{BroadcastChannel, MessageEvent};
+use yew::Callback;
+
+use crate::signaling::presence::LocalPresence;
+use crate::signaling::room::{LocalRoom, RoomEnvelope, RoomOutput};
+use crate::signaling::{emit_later, SignalingTransport, Subscriber, TransportState};
+use crate::utils::error::{js_error_message, CustomError};
+use crate::utils::socket::SocketMessage;
+
+/// Signaling between the tabs of the same browser, to test two peers on one machine.
+/// There is no server, so each tab plays its part of the room logic.
+pub struct BroadcastChannelTransport {
+    channel: Rc<BroadcastChannel>,
+    room: Rc<RefCell<LocalRoom>>,
+    presence: Rc<RefCell<LocalPresence>>,
+    subscriber: Subscriber<SocketMessage>,
+    // The channel is open as long as the transport lives, there is no state change to report
+    state_subscriber: Subscriber<TransportState>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+fn dispatch(outputs: Vec<RoomOutput>, channel: &BroadcastChannel, subscriber: &Subscriber<SocketMessage>) {
+    for output in outputs {
+        match output {
+            RoomOutput::Deliver(message) => emit_later(subscriber, message),
+            RoomOutput::Broadcast(envelope) => match serde_json::to_string(&envelope) {
+                Ok(json_message) => {
+                    if let Err(err) = channel.post_message(&JsValue::from_str(&json_message)) {
+                        log::error!("Could not send message to the broadcast channel {:?}", err);
+                    }
+                }
+                Err(err) => log::error!("Could not serialize socket message {:?}", err),
+            },
+        }
+    }
+}
+
+impl BroadcastChannelTransport {
+    pub fn new(name: &str) -> Result<Self, CustomError> {
+        let channel = BroadcastChannel::new(name).map_err(|err| CustomError::SignalingError(js_error_message(&err)))?;
+        let channel = Rc::new(channel);
+        let room = Rc::new(RefCell::new(LocalRoom::default()));
+        let presence = Rc::new(RefCell::new(LocalPresence::default()));
+        let subscriber = Subscriber::default();
+
+        let on_message_channel = channel.clone();
+        let on_message_room = room.clone();
+        let on_message_presence = presence.clone();
+        let on_message_subscriber = subscriber.clone();
+        let on_message: Closure<dyn FnMut(MessageEvent)> = Closure::wrap(Box::new(move |message: MessageEvent| {
+            let envelope = message
+                .data()
+                .as_string()
+                .and_then(|data| serde_json::from_str::<RoomEnvelope>(&data).ok());
+            match envelope {
+                Some(envelope) => {
+                    let mut outputs = on_message_room.borrow_mut().on_incoming(envelope.clone());
+                    outputs.extend(on_message_presence.borrow_mut().on_incoming(envelope));
+                    dispatch(outputs, &on_message_channel, &on_message_subscriber);
+                }
+                None => log::error!("Oh No: unreadable broadcast message {:?}", message.data()),
+            }
+        }));
+        channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            channel,
+            room,
+            presence,
+            subscriber,
+            state_subscriber: Subscriber::default(),
+            _on_message: on_message,
+        })
+    }
+}
+
+impl SignalingTransport for BroadcastChannelTransport {
+    fn send(&self, message: &SocketMessage) {
+        let mut outputs = self.room.borrow_mut().on_outgoing(message.clone());
+        outputs.extend(self.presence.borrow_mut().on_outgoing(message.clone()));
+        dispatch(outputs, &self.channel, &self.subscriber);
+    }
+
+    fn subscribe(&self, on_message: Option<Callback<SocketMessage>>) {
+        *self.subscriber.borrow_mut() = on_message;
+    }
+
+    fn state(&self) -> TransportState {
+        TransportState::Open
+    }
+
+    fn subscribe_state(&self, on_state_change: Option<Callback<TransportState>>) {
+        *self.state_subscriber.borrow_mut() = on_state_change;
+    }
+}
+
+impl Drop for BroadcastChannelTransport {
+    fn drop(&mut self) {
+        // A closed tab does not get here, its contacts still see it online
+        let outputs = self.presence.borrow_mut().leave();
+        dispatch(outputs, &self.channel, &self.subscriber);
+        self.channel.set_onmessage(None);
+        self.channel.close();
+    }
+}
@@ -0,0 +1,144 @@
+use std::{cell::{Cell, RefCell}, collections::HashMap, rc::{Rc, Weak}};
+
+use wasm_bindgen_futures::spawn_local;
+use yew::Callback;
+
+use crate::signaling::presence::LocalPresence;
+use crate::signaling::room::{LocalRoom, RoomOutput};
+use crate::signaling::{emit, emit_later, SignalingTransport, Subscriber, TransportState};
+use crate::utils::socket::SocketMessage;
+
+thread_local! {
+    // Endpoints of the page by hub name, they only hear each other
+    static HUBS: RefCell<HashMap<String, Vec<Weak<Endpoint>>>> = RefCell::new(HashMap::new());
+}
+
+#[derive(Default)]
+struct Endpoint {
+    room: RefCell<LocalRoom>,
+    presence: RefCell<LocalPresence>,
+    subscriber: Subscriber<SocketMessage>,
+    state_subscriber: Subscriber<TransportState>,
+    closed: Cell<bool>,
+}
+
+/// Signaling between the transports of the same page, without network.
+/// Two `WebRTC` instances configured with the same hub find each other as with the server.
+pub struct InMemoryTransport {
+    hub: String,
+    endpoint: Rc<Endpoint>,
+}
+
+fn peers(hub: &str, endpoint: &Rc<Endpoint>) -> Vec<Rc<Endpoint>> {
+    HUBS.with(|hubs| {
+        hubs.borrow()
+            .get(hub)
+            .map(|endpoints| {
+                endpoints
+                    .iter()
+                    .filter_map(Weak::upgrade)
+                    .filter(|peer| !Rc::ptr_eq(peer, endpoint))
+                    .collect()
+            })
+            .unwrap_or_default()
+    })
+}
+
+// Broadcasts are delivered asynchronously, as they would be over a network
+fn dispatch(hub: &str, endpoint: &Rc<Endpoint>, outputs: Vec<RoomOutput>) {
+    for output in outputs {
+        match output {
+            RoomOutput::Deliver(message) => emit_later(&endpoint.subscriber, message),
+            RoomOutput::Broadcast(envelope) => {
+                for peer in peers(hub, endpoint) {
+                    let hub = hub.to_string();
+                    let envelope = envelope.clone();
+                    spawn_local(async move {
+                        if peer.closed.get() {
+                            return;
+                        }
+                        let mut outputs = peer.room.borrow_mut().on_incoming(envelope.clone());
+                        outputs.extend(peer.presence.borrow_mut().on_incoming(envelope));
+                        dispatch(&hub, &peer, outputs);
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl InMemoryTransport {
+    pub fn new(hub: &str) -> Self {
+        let endpoint = Rc::new(Endpoint::default());
+        HUBS.with(|hubs| {
+            hubs.borrow_mut()
+                .entry(hub.to_string())
+                .or_default()
+                .push(Rc::downgrade(&endpoint));
+        });
+        Self {
+            hub: hub.to_string(),
+            endpoint,
+        }
+    }
+
+    /// Leaves the hub, as if the server went away
+    pub fn close(&self) {
+        if self.endpoint.closed.replace(true) {
+            return;
+        }
+        self.leave();
+        emit(&self.endpoint.state_subscriber, TransportState::Closed);
+    }
+
+    // Tells the contacts before leaving the hub
+    fn leave(&self) {
+        let outputs = self.endpoint.presence.borrow_mut().leave();
+        dispatch(&self.hub, &self.endpoint, outputs);
+        self.detach();
+    }
+
+    fn detach(&self) {
+        HUBS.with(|hubs| {
+            if let Some(endpoints) = hubs.borrow_mut().get_mut(&self.hub) {
+                endpoints.retain(|endpoint| {
+                    endpoint.upgrade().is_some_and(|endpoint| !Rc::ptr_eq(&endpoint, &self.endpoint))
+                });
+            }
+        });
+    }
+}
+
+impl SignalingTransport for InMemoryTransport {
+    fn send(&self, message: &SocketMessage) {
+        if self.endpoint.closed.get() {
+            log::error!("Could not send message to a closed in-memory transport");
+            return;
+        }
+        let mut outputs = self.endpoint.room.borrow_mut().on_outgoing(message.clone());
+        outputs.extend(self.endpoint.presence.borrow_mut().on_outgoing(message.clone()));
+        dispatch(&self.hub, &self.endpoint, outputs);
+    }
+
+    fn subscribe(&self, on_message: Option<Callback<SocketMessage>>) {
+        *self.endpoint.subscriber.borrow_mut() = on_message;
+    }
+
+    fn state(&self) -> TransportState {
+        if self.endpoint.closed.get() {
+            TransportState::Closed
+        } else {
+            TransportState::Open
+        }
+    }
+
+    fn subscribe_state(&self, on_state_change: Option<Callback<TransportState>>) {
+        *self.endpoint.state_subscriber.borrow_mut() = on_state_change;
+    }
+}
+
+impl Drop for InMemoryTransport {
+    fn drop(&mut self) {
+        self.leave();
+    }
+}
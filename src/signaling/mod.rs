@@ -0,0 +1,103 @@
+use std::{cell::RefCell, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::Callback;
+
+use crate::utils::error::CustomError;
+use crate::utils::query::query_parameter;
+use crate::utils::socket::SocketMessage;
+
+pub mod broadcast_channel;
+pub mod in_memory;
+pub mod presence;
+pub mod room;
+pub mod websocket;
+
+use broadcast_channel::BroadcastChannelTransport;
+use in_memory::InMemoryTransport;
+use websocket::WebSocketTransport;
+
+pub const DEFAULT_SIGNALING_URL: &str = "wss://glacial-beyond-33808.herokuapp.com";
+const BROADCAST_CHANNEL_NAME: &str = "prust-signaling";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransportState {
+    Connecting,
+    Open,
+    Closed,
+}
+
+/// Carries the `SocketMessage`s between `WebRTC` and the other peer
+pub trait SignalingTransport {
+    fn send(&self, message: &SocketMessage);
+    /// Replaces the receiver of the incoming messages, `None` stops the delivery
+    fn subscribe(&self, on_message: Option<Callback<SocketMessage>>);
+    fn state(&self) -> TransportState;
+    /// Replaces the receiver of the state changes, `None` stops the delivery
+    fn subscribe_state(&self, on_state_change: Option<Callback<TransportState>>);
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SignalingConfig {
+    WebSocket(String),
+    /// Between the tabs of the same browser, no server needed
+    BroadcastChannel(String),
+    /// Between the transports of the same page sharing a hub name, for tests
+    InMemory(String),
+}
+
+impl Default for SignalingConfig {
+    fn default() -> Self {
+        SignalingConfig::WebSocket(String::from(DEFAULT_SIGNALING_URL))
+    }
+}
+
+impl SignalingConfig {
+    /// Reads the `signaling` parameter of a query string, `?signaling=broadcast` for instance
+    pub fn from_query(query: &str) -> Self {
+        match query_parameter(query, "signaling") {
+            Some("broadcast") => SignalingConfig::BroadcastChannel(String::from(BROADCAST_CHANNEL_NAME)),
+            _ => SignalingConfig::default(),
+        }
+    }
+
+    pub fn connect(&self) -> Result<Box<dyn SignalingTransport>, CustomError> {
+        Ok(match self {
+            SignalingConfig::WebSocket(url) => Box::new(WebSocketTransport::new(url)?),
+            SignalingConfig::BroadcastChannel(name) => Box::new(BroadcastChannelTransport::new(name)?),
+            SignalingConfig::InMemory(hub) => Box::new(InMemoryTransport::new(hub)),
+        })
+    }
+}
+
+type Subscriber<T> = Rc<RefCell<Option<Callback<T>>>>;
+
+// The callback is cloned first, so it can subscribe again while it runs
+fn emit<T>(subscriber: &Subscriber<T>, value: T) {
+    let callback = subscriber.borrow().clone();
+    if let Some(callback) = callback {
+        callback.emit(value);
+    }
+}
+
+// Messages of the local peer may be answered while it is still sending, deliver them afterwards
+fn emit_later<T: 'static>(subscriber: &Subscriber<T>, value: T) {
+    let subscriber = subscriber.clone();
+    spawn_local(async move { emit(&subscriber, value) });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_signaling_from_the_query() {
+        assert_eq!(SignalingConfig::from_query(""), SignalingConfig::default());
+        assert_eq!(
+            SignalingConfig::from_query("?debug=true&signaling=broadcast"),
+            SignalingConfig::BroadcastChannel(String::from(BROADCAST_CHANNEL_NAME))
+        );
+        assert_eq!(SignalingConfig::from_query("?signaling=carrier-pigeon"), SignalingConfig::default());
+    }
+}
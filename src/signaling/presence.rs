@@ -0,0 +1,168 @@
+use crate::signaling::room::{RoomEnvelope, RoomOutput};
+use crate::utils::socket::{Presence, PresenceSubscription, SocketMessage};
+
+// Not a valid room id, no conversation can end up in it
+const PRESENCE_ROOM: &str = ":presence";
+
+/// Does what the signaling server does with the presence, for transports without one:
+/// the users online answer each newcomer, and tell the others when they leave.
+#[derive(Default)]
+pub struct LocalPresence {
+    subscription: Option<PresenceSubscription>,
+}
+
+fn envelope(user: &str, message: SocketMessage) -> RoomEnvelope {
+    RoomEnvelope {
+        room: String::from(PRESENCE_ROOM),
+        from: user.to_string(),
+        message,
+    }
+}
+
+fn notify(user: &str, online: bool) -> SocketMessage {
+    SocketMessage::PresenceNotify {
+        content: Presence {
+            user: user.to_string(),
+            online,
+        },
+    }
+}
+
+impl LocalPresence {
+    /// Handles a message the local user sends to the server
+    pub fn on_outgoing(&mut self, message: SocketMessage) -> Vec<RoomOutput> {
+        match message {
+            SocketMessage::PresenceSubscribe { content } => {
+                let announce = envelope(&content.user, SocketMessage::PresenceSubscribe { content: content.clone() });
+                self.subscription = Some(content);
+                vec![RoomOutput::Broadcast(announce)]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Handles a message broadcast by another user
+    pub fn on_incoming(&mut self, envelope: RoomEnvelope) -> Vec<RoomOutput> {
+        let subscription = match &self.subscription {
+            Some(subscription) => subscription,
+            None => return vec![],
+        };
+        if envelope.room != PRESENCE_ROOM || envelope.from == subscription.user {
+            return vec![];
+        }
+        let watched = subscription.contacts.contains(&envelope.from);
+
+        match envelope.message {
+            SocketMessage::PresenceSubscribe { .. } => {
+                let mut outputs = vec![];
+                if watched {
+                    outputs.push(RoomOutput::Deliver(notify(&envelope.from, true)));
+                }
+                // The newcomer may be watching us, it does not know we are here
+                outputs.push(RoomOutput::Broadcast(self::envelope(
+                    &subscription.user,
+                    notify(&subscription.user, true),
+                )));
+                outputs
+            }
+            // Nobody can speak for someone else
+            SocketMessage::PresenceNotify { content } if watched && content.user == envelope.from => {
+                vec![RoomOutput::Deliver(SocketMessage::PresenceNotify { content })]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// To broadcast when the transport goes away
+    pub fn leave(&mut self) -> Vec<RoomOutput> {
+        match self.subscription.take() {
+            Some(subscription) => vec![RoomOutput::Broadcast(envelope(
+                &subscription.user,
+                notify(&subscription.user, false),
+            ))],
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscribe(user: &str, contacts: &[&str]) -> (LocalPresence, Vec<RoomEnvelope>) {
+        let mut presence = LocalPresence::default();
+        let outputs = presence.on_outgoing(SocketMessage::PresenceSubscribe {
+            content: PresenceSubscription {
+                user: user.to_string(),
+                contacts: contacts.iter().map(|contact| contact.to_string()).collect(),
+            },
+        });
+        (presence, broadcasts(outputs))
+    }
+
+    fn broadcasts(outputs: Vec<RoomOutput>) -> Vec<RoomEnvelope> {
+        outputs
+            .into_iter()
+            .filter_map(|output| match output {
+                RoomOutput::Broadcast(envelope) => Some(envelope),
+                RoomOutput::Deliver(_) => None,
+            })
+            .collect()
+    }
+
+    fn delivered(outputs: &[RoomOutput]) -> Vec<Presence> {
+        outputs
+            .iter()
+            .filter_map(|output| match output {
+                RoomOutput::Deliver(SocketMessage::PresenceNotify { content }) => Some(content.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn presence(user: &str, online: bool) -> Presence {
+        Presence {
+            user: user.to_string(),
+            online,
+        }
+    }
+
+    #[test]
+    fn both_users_see_each_other_whoever_comes_first() {
+        let (mut alice, _) = subscribe("alice", &["bob"]);
+        let (mut bob, bob_announce) = subscribe("bob", &["alice"]);
+
+        let outputs = alice.on_incoming(bob_announce[0].clone());
+        assert_eq!(delivered(&outputs), vec![presence("bob", true)]);
+        let outputs = bob.on_incoming(broadcasts(outputs).remove(0));
+        assert_eq!(delivered(&outputs), vec![presence("alice", true)]);
+    }
+
+    #[test]
+    fn only_the_contacts_are_delivered() {
+        let (mut alice, _) = subscribe("alice", &["bob"]);
+        let (_, carol_announce) = subscribe("carol", &["alice"]);
+
+        let outputs = alice.on_incoming(carol_announce[0].clone());
+        assert!(delivered(&outputs).is_empty());
+        // Carol watches alice, she is answered anyway
+        assert_eq!(broadcasts(outputs).len(), 1);
+    }
+
+    #[test]
+    fn tells_the_contacts_when_leaving() {
+        let (mut alice, _) = subscribe("alice", &["bob"]);
+        let (mut bob, _) = subscribe("bob", &["alice"]);
+
+        let outputs = alice.on_incoming(broadcasts(bob.leave()).remove(0));
+        assert_eq!(delivered(&outputs), vec![presence("bob", false)]);
+        assert!(bob.leave().is_empty());
+    }
+
+    #[test]
+    fn ignores_notifications_about_someone_else() {
+        let (mut alice, _) = subscribe("alice", &["bob"]);
+        let forged = envelope("carol", notify("bob", false));
+        assert!(alice.on_incoming(forged).is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::participants::Participants;
+use crate::utils::socket::{Room, SignalingMessage, SocketMessage};
+
+// Id of the negotiated data channel, the server gives the same one to both peers of a room
+const DATA_CHANNEL_ID: u16 = 0;
+
+/// A `SocketMessage` sent to the other peers of a room, by transports without server
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RoomEnvelope {
+    pub room: String,
+    pub from: String,
+    pub message: SocketMessage,
+}
+
+#[derive(Debug)]
+pub enum RoomOutput {
+    /// To hand to the local peer, as if it came from the server
+    Deliver(SocketMessage),
+    /// To send to the other peers
+    Broadcast(RoomEnvelope),
+}
+
+/// Does what the signaling server does, for transports without one:
+/// puts two users in the same room, tells them when the other one is here and relays their messages.
+#[derive(Default)]
+pub struct LocalRoom {
+    participants: Option<Participants>,
+    room: Option<String>,
+    peer_here: bool,
+}
+
+pub fn room_name(participants: &Participants) -> String {
+    if let Some(room) = &participants.room {
+        return room.clone();
+    }
+    // Both users must end up in the same room, whoever connects first
+    let mut names = [participants.user_from.as_str(), participants.user_to.as_str()];
+    names.sort_unstable();
+    format!("{}:{}", names[0], names[1])
+}
+
+impl LocalRoom {
+    /// Handles a message the local peer sends to the server
+    pub fn on_outgoing(&mut self, message: SocketMessage) -> Vec<RoomOutput> {
+        match message {
+            SocketMessage::NewUser { content } => {
+                let room = room_name(&content);
+                self.participants = Some(content.clone());
+                self.room = Some(room.clone());
+                self.peer_here = false;
+                vec![
+                    RoomOutput::Deliver(SocketMessage::JoinedRoom {
+                        content: Room { room: room.clone() },
+                    }),
+                    RoomOutput::Broadcast(RoomEnvelope {
+                        room,
+                        from: content.user_from.clone(),
+                        message: SocketMessage::NewUser { content },
+                    }),
+                ]
+            }
+            SocketMessage::SignalMessageFromClient { content } => self
+                .envelope(SocketMessage::SignalMessageToClient { content })
+                .map(RoomOutput::Broadcast)
+                .into_iter()
+                .collect(),
+            SocketMessage::LeaveRoom { .. } => {
+                *self = LocalRoom::default();
+                vec![]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Handles a message broadcast by another peer
+    pub fn on_incoming(&mut self, envelope: RoomEnvelope) -> Vec<RoomOutput> {
+        let participants = match &self.participants {
+            Some(participants) => participants.clone(),
+            None => return vec![],
+        };
+        // In a shared room the name of the friend is not known, anyone else is
+        let from_friend = match participants.room {
+            Some(_) => envelope.from != participants.user_from,
+            None => envelope.from == participants.user_to,
+        };
+        if self.room.as_ref() != Some(&envelope.room) || !from_friend {
+            return vec![];
+        }
+
+        match envelope.message {
+            SocketMessage::NewUser { .. } => {
+                // Already introduced, this is the answer to our own announce
+                if self.peer_here {
+                    return vec![];
+                }
+                self.peer_here = true;
+                let mut outputs = vec![RoomOutput::Deliver(SocketMessage::SignalMessageToClient {
+                    content: SignalingMessage::UserHere { message: DATA_CHANNEL_ID },
+                })];
+                // The other peer may have joined before us, announce ourselves back
+                let announce = SocketMessage::NewUser { content: participants };
+                outputs.extend(self.envelope(announce).map(RoomOutput::Broadcast));
+                outputs
+            }
+            SocketMessage::SignalMessageToClient { content } => {
+                if let SignalingMessage::UserLeft = content {
+                    self.peer_here = false;
+                }
+                vec![RoomOutput::Deliver(SocketMessage::SignalMessageToClient { content })]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn envelope(&self, message: SocketMessage) -> Option<RoomEnvelope> {
+        match (&self.room, &self.participants) {
+            (Some(room), Some(participants)) => Some(RoomEnvelope {
+                room: room.clone(),
+                from: participants.user_from.clone(),
+                message,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn join(from: &str, to: &str) -> (LocalRoom, Vec<RoomOutput>) {
+        let mut room = LocalRoom::default();
+        let outputs = room.on_outgoing(SocketMessage::NewUser {
+            content: Participants {
+                user_from: from.to_string(),
+                user_to: to.to_string(),
+                room: None,
+            },
+        });
+        (room, outputs)
+    }
+
+    fn join_shared(from: &str, room_id: &str) -> (LocalRoom, Vec<RoomOutput>) {
+        let mut room = LocalRoom::default();
+        let outputs = room.on_outgoing(SocketMessage::NewUser {
+            content: Participants {
+                user_from: from.to_string(),
+                user_to: String::new(),
+                room: Some(room_id.to_string()),
+            },
+        });
+        (room, outputs)
+    }
+
+    fn broadcasts(outputs: Vec<RoomOutput>) -> Vec<RoomEnvelope> {
+        outputs
+            .into_iter()
+            .filter_map(|output| match output {
+                RoomOutput::Broadcast(envelope) => Some(envelope),
+                RoomOutput::Deliver(_) => None,
+            })
+            .collect()
+    }
+
+    fn user_here_count(outputs: &[RoomOutput]) -> usize {
+        outputs
+            .iter()
+            .filter(|output| {
+                matches!(
+                    output,
+                    RoomOutput::Deliver(SocketMessage::SignalMessageToClient {
+                        content: SignalingMessage::UserHere { .. }
+                    })
+                )
+            })
+            .count()
+    }
+
+    #[test]
+    fn both_users_join_the_same_room() {
+        let (_, alice) = join("alice", "bob");
+        let (_, bob) = join("bob", "alice");
+        let room_of = |outputs: &[RoomOutput]| match &outputs[0] {
+            RoomOutput::Deliver(SocketMessage::JoinedRoom { content }) => content.room.clone(),
+            other => panic!("Unexpected output {:?}", other),
+        };
+        assert_eq!(room_of(&alice), room_of(&bob));
+    }
+
+    #[test]
+    fn tells_both_users_the_other_one_is_here() {
+        let (mut alice, alice_outputs) = join("alice", "bob");
+        let (mut bob, bob_outputs) = join("bob", "alice");
+        // Nobody listened to the announce of alice, she joined first
+        drop(alice_outputs);
+
+        let mut alice_user_here = 0;
+        let mut bob_user_here = 0;
+        let mut to_alice = broadcasts(bob_outputs);
+        let mut to_bob = vec![];
+        while !to_alice.is_empty() || !to_bob.is_empty() {
+            for envelope in std::mem::take(&mut to_alice) {
+                let outputs = alice.on_incoming(envelope);
+                alice_user_here += user_here_count(&outputs);
+                to_bob.extend(broadcasts(outputs));
+            }
+            for envelope in std::mem::take(&mut to_bob) {
+                let outputs = bob.on_incoming(envelope);
+                bob_user_here += user_here_count(&outputs);
+                to_alice.extend(broadcasts(outputs));
+            }
+        }
+        assert_eq!(alice_user_here, 1);
+        assert_eq!(bob_user_here, 1);
+    }
+
+    #[test]
+    fn ignores_other_rooms_and_own_messages() {
+        let (mut alice, alice_outputs) = join("alice", "bob");
+        let (_, carol_outputs) = join("carol", "bob");
+        for envelope in broadcasts(alice_outputs).into_iter().chain(broadcasts(carol_outputs)) {
+            assert!(alice.on_incoming(envelope).is_empty());
+        }
+    }
+
+    #[test]
+    fn relays_signaling_messages() {
+        let (mut alice, _) = join("alice", "bob");
+        let (mut bob, _) = join("bob", "alice");
+        let outputs = bob.on_outgoing(SocketMessage::SignalMessageFromClient {
+            content: SignalingMessage::UserLeft,
+        });
+        let outputs = alice.on_incoming(broadcasts(outputs).remove(0));
+        assert!(matches!(
+            outputs.as_slice(),
+            [RoomOutput::Deliver(SocketMessage::SignalMessageToClient {
+                content: SignalingMessage::UserLeft
+            })]
+        ));
+    }
+
+    #[test]
+    fn users_of_a_shared_room_find_each_other_without_names() {
+        let (mut alice, _) = join_shared("alice", "0a1b");
+        let (_, bob_outputs) = join_shared("bob", "0a1b");
+        let (_, carol_outputs) = join_shared("carol", "ffff");
+
+        assert!(alice.on_incoming(broadcasts(carol_outputs).remove(0)).is_empty());
+        let outputs = alice.on_incoming(broadcasts(bob_outputs).remove(0));
+        assert_eq!(user_here_count(&outputs), 1);
+    }
+}
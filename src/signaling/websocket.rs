@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{MessageEvent, WebSocket};
+use yew::Callback;
+
+use crate::signaling::{emit, SignalingTransport, Subscriber, TransportState};
+use crate::utils::error::{js_error_message, CustomError};
+use crate::utils::socket::SocketMessage;
+
+/// Talks to the signaling server
+pub struct WebSocketTransport {
+    socket: WebSocket,
+    // Sent while the socket was connecting, flushed once open
+    pending: Rc<RefCell<Vec<String>>>,
+    subscriber: Subscriber<SocketMessage>,
+    state_subscriber: Subscriber<TransportState>,
+    _on_open: Closure<dyn FnMut(JsValue)>,
+    _on_close: Closure<dyn FnMut(JsValue)>,
+    _on_error: Closure<dyn FnMut(JsValue)>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl WebSocketTransport {
+    pub fn new(url: &str) -> Result<Self, CustomError> {
+        // Only an invalid URL throws, unreachable servers are reported with a close event
+        let socket = WebSocket::new(url).map_err(|err| CustomError::SignalingError(js_error_message(&err)))?;
+
+        let state_subscriber = Subscriber::default();
+        let pending = Rc::new(RefCell::new(Vec::<String>::new()));
+
+        // Is equivalent to onConnect in JS
+        let on_open_subscriber = state_subscriber.clone();
+        let on_open_socket = socket.clone();
+        let on_open_pending = pending.clone();
+        let on_open: Closure<dyn FnMut(JsValue)> = Closure::wrap(Box::new(move |_| {
+            log::info!("socket opened");
+            for json_message in on_open_pending.borrow_mut().drain(..) {
+                if let Err(err) = on_open_socket.send_with_str(&json_message) {
+                    log::error!("Could not send message to websocket {:?}", err);
+                }
+            }
+            emit(&on_open_subscriber, TransportState::Open);
+        }));
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+
+        let on_close_subscriber = state_subscriber.clone();
+        let on_close: Closure<dyn FnMut(JsValue)> = Closure::wrap(Box::new(move |_| {
+            log::info!("socket closed");
+            emit(&on_close_subscriber, TransportState::Closed);
+        }));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        // Always followed by a close event
+        let on_error: Closure<dyn FnMut(JsValue)> = Closure::wrap(Box::new(move |err| {
+            log::error!("socket error {:?}", err);
+        }));
+        socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+        let subscriber = Subscriber::default();
+        let on_message_subscriber = subscriber.clone();
+        let on_message: Closure<dyn FnMut(MessageEvent)> = Closure::wrap(Box::new(move |message: MessageEvent| {
+            match SocketMessage::try_from(message) {
+                Ok(parsed) => emit(&on_message_subscriber, parsed),
+                Err(error) => log::error!("Ignoring a signaling message: {}", error),
+            };
+        }));
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            socket,
+            pending,
+            subscriber,
+            state_subscriber,
+            _on_open: on_open,
+            _on_close: on_close,
+            _on_error: on_error,
+            _on_message: on_message,
+        })
+    }
+}
+
+impl SignalingTransport for WebSocketTransport {
+    fn send(&self, message: &SocketMessage) {
+        let json_message = match serde_json::to_string(message) {
+            Ok(json_message) => json_message,
+            Err(err) => {
+                log::error!("Could not serialize socket message {:?}", err);
+                return;
+            }
+        };
+        if self.socket.ready_state() == WebSocket::CONNECTING {
+            self.pending.borrow_mut().push(json_message);
+            return;
+        }
+        if let Err(err) = self.socket.send_with_str(&json_message) {
+            log::error!("Could not send message to websocket {:?}", err);
+        }
+    }
+
+    fn subscribe(&self, on_message: Option<Callback<SocketMessage>>) {
+        *self.subscriber.borrow_mut() = on_message;
+    }
+
+    fn state(&self) -> TransportState {
+        match self.socket.ready_state() {
+            WebSocket::CONNECTING => TransportState::Connecting,
+            WebSocket::OPEN => TransportState::Open,
+            _ => TransportState::Closed,
+        }
+    }
+
+    fn subscribe_state(&self, on_state_change: Option<Callback<TransportState>>) {
+        *self.state_subscriber.borrow_mut() = on_state_change;
+    }
+}
+
+impl Drop for WebSocketTransport {
+    fn drop(&mut self) {
+        self.socket.set_onopen(None);
+        self.socket.set_onclose(None);
+        self.socket.set_onerror(None);
+        self.socket.set_onmessage(None);
+        let _ = self.socket.close();
+    }
+}
@@ -0,0 +1,49 @@
+use std::any::Any;
+use std::cell::Cell;
+
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+thread_local! {
+    static LIVE_CLOSURES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Closures registered and not freed yet, in every registry of the page.
+/// It should go back down after a disconnection, a growing count is a leak.
+pub fn live_closures() -> usize {
+    LIVE_CLOSURES.with(|live| live.get())
+}
+
+struct Registered {
+    _closure: Box<dyn Any>,
+}
+
+impl Drop for Registered {
+    fn drop(&mut self) {
+        LIVE_CLOSURES.with(|live| live.set(live.get().saturating_sub(1)));
+    }
+}
+
+/// Keeps the closures given to JavaScript alive instead of forgetting them.
+/// They are freed by `clear`, once every handler using them has been unregistered.
+#[derive(Default)]
+pub struct CallbackRegistry {
+    closures: Vec<Registered>,
+}
+
+impl CallbackRegistry {
+    /// Returns the function to give to JavaScript, valid until the registry is cleared
+    pub fn register<T: ?Sized + 'static>(&mut self, closure: Closure<T>) -> Function {
+        let function = closure.as_ref().unchecked_ref::<Function>().clone();
+        LIVE_CLOSURES.with(|live| live.set(live.get() + 1));
+        self.closures.push(Registered {
+            _closure: Box::new(closure),
+        });
+        function
+    }
+
+    pub fn clear(&mut self) {
+        self.closures.clear();
+    }
+}
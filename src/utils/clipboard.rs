@@ -0,0 +1,24 @@
+use js_sys::{Function, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Writes `text` to the clipboard with `navigator.clipboard.writeText`.
+/// The Clipboard API is still behind an unstable flag in web-sys, so it is reached dynamically.
+pub fn copy_to_clipboard(text: &str) {
+    let clipboard = web_sys::window()
+        .and_then(|window| Reflect::get(&window.navigator(), &JsValue::from_str("clipboard")).ok())
+        .filter(|clipboard| !clipboard.is_undefined());
+    let write_text = clipboard.as_ref().and_then(|clipboard| {
+        Reflect::get(clipboard, &JsValue::from_str("writeText"))
+            .ok()
+            .and_then(|write_text| write_text.dyn_into::<Function>().ok())
+    });
+
+    match (clipboard, write_text) {
+        (Some(clipboard), Some(write_text)) => {
+            if let Err(err) = write_text.call1(&clipboard, &JsValue::from_str(text)) {
+                log::error!("Could not copy to the clipboard {:?}", err);
+            }
+        }
+        _ => log::error!("The clipboard is not available"),
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt::{Display, Formatter, Result};
+
+use serde::{Deserialize, Serialize};
+
+/// State of the connection with the other peer, as shown to the user.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ConnectionState {
+    New,
+    Signaling,
+    Connecting,
+    Connected,
+    Disconnected,
+    Failed,
+    Closed,
+}
+
+impl ConnectionState {
+    /// Maps `RTCPeerConnection.connectionState` and `RTCPeerConnection.iceConnectionState` values.
+    /// "new" is ignored because it says nothing more than the state we already track.
+    pub fn from_peer_state(peer_state: &str) -> Option<Self> {
+        match peer_state {
+            "checking" | "connecting" => Some(ConnectionState::Connecting),
+            "connected" | "completed" => Some(ConnectionState::Connected),
+            "disconnected" => Some(ConnectionState::Disconnected),
+            "failed" => Some(ConnectionState::Failed),
+            "closed" => Some(ConnectionState::Closed),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConnectionState::New => "Not connected",
+            ConnectionState::Signaling => "Waiting for your friend...",
+            ConnectionState::Connecting => "Connecting...",
+            ConnectionState::Connected => "Connected",
+            ConnectionState::Disconnected => "Connection lost, trying to recover...",
+            ConnectionState::Failed => "Connection failed",
+            ConnectionState::Closed => "Disconnected",
+        }
+    }
+}
+
+impl Display for ConnectionState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ConnectionState::New => write!(f, "new"),
+            ConnectionState::Signaling => write!(f, "signaling"),
+            ConnectionState::Connecting => write!(f, "connecting"),
+            ConnectionState::Connected => write!(f, "connected"),
+            ConnectionState::Disconnected => write!(f, "disconnected"),
+            ConnectionState::Failed => write!(f, "failed"),
+            ConnectionState::Closed => write!(f, "closed"),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::storage;
+
+const CONTACTS_KEY: &str = "prust-contacts";
+
+/// The friends of the user, kept in localStorage so their names are typed once
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContactBook {
+    /// Last name the user connected with, announced to the contacts
+    #[serde(default)]
+    pub me: Option<String>,
+    #[serde(default)]
+    pub contacts: Vec<String>,
+}
+
+impl ContactBook {
+    pub fn load() -> Self {
+        match storage::load(CONTACTS_KEY) {
+            Ok(contact_book) => contact_book.unwrap_or_default(),
+            Err(err) => {
+                log::error!("Could not read the contacts: {}", err);
+                ContactBook::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = storage::save(CONTACTS_KEY, self) {
+            log::error!("Could not save the contacts: {}", err);
+        }
+    }
+
+    /// Whether the contact was added, the names are as the server knows them
+    pub fn add(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        if name.is_empty() || self.me.as_deref() == Some(name) || self.contacts.iter().any(|contact| contact == name) {
+            return false;
+        }
+        self.contacts.push(name.to_string());
+        true
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.contacts.retain(|contact| contact != name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_each_contact_once() {
+        let mut contact_book = ContactBook {
+            me: Some(String::from("alice")),
+            contacts: vec![],
+        };
+        assert!(contact_book.add(" bob "));
+        assert!(!contact_book.add("bob"));
+        assert!(!contact_book.add("alice"));
+        assert!(!contact_book.add("  "));
+        assert_eq!(contact_book.contacts, vec![String::from("bob")]);
+
+        contact_book.remove("bob");
+        assert!(contact_book.contacts.is_empty());
+    }
+
+    #[test]
+    fn reads_an_empty_book() {
+        assert_eq!(serde_json::from_str::<ContactBook>("{}").unwrap(), ContactBook::default());
+    }
+}
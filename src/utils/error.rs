@@ -0,0 +1,168 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Everything that can go wrong, grouped by the part of the application that failed.
+/// `Display` is meant for the logs, `user_message` for the user.
+#[derive(Debug)]
+pub enum CustomError {
+    // Signaling
+    SignalingError(String),
+    InputTypeError(String),
+    // Serialization
+    JsonParseError(serde_json::Error),
+    SessionCodeError(String),
+    RoomCodeError(String),
+    QrCodeError(String),
+    // Negotiation
+    NegotiationError(String),
+    SdpParseError { line: usize, reason: String },
+    SdpValidationError(String),
+    // ICE
+    IceError(String),
+    CandidateParseError(String),
+    // The connection failed and only a new session can restore it
+    ConnectionLostError(String),
+    // Data channel
+    DataChannelError(String),
+    // Media
+    MediaError(String),
+    // Storage
+    StorageError(String),
+    SettingsError(String),
+    // Any other exception thrown by a browser API
+    JsError(String),
+}
+
+impl CustomError {
+    /// What happened and what the user can do about it
+    pub fn user_message(&self) -> String {
+        match self {
+            CustomError::SignalingError(_) => String::from(
+                "Could not reach the signaling server. Check your connection or use the manual mode.",
+            ),
+            CustomError::InputTypeError(_) | CustomError::JsonParseError(_) => String::from(
+                "Received an unreadable message. Disconnect and connect again.",
+            ),
+            CustomError::SessionCodeError(reason) => {
+                format!("This code is invalid, {}. Ask your friend to send it again.", reason)
+            }
+            CustomError::RoomCodeError(reason) => {
+                format!("This room code is invalid, {}. Check it with your friend.", reason)
+            }
+            CustomError::QrCodeError(_) => String::from("Could not draw the QR code, copy the code instead."),
+            CustomError::NegotiationError(_) => String::from(
+                "Could not agree on a connection with your friend. Disconnect and connect again.",
+            ),
+            CustomError::SdpParseError { .. } | CustomError::SdpValidationError(_) => String::from(
+                "Your friend sent an unreadable connection offer. Ask them to connect again.",
+            ),
+            CustomError::IceError(_) => String::from(
+                "Could not reach your friend over the network. Share every address in the privacy settings, \
+                 or try another network.",
+            ),
+            CustomError::CandidateParseError(_) => {
+                String::from("An unreadable network address of your friend was ignored.")
+            }
+            CustomError::ConnectionLostError(_) => String::from(
+                "The connection with your friend was lost and cannot be restored automatically. \
+                 Disconnect and exchange new session codes.",
+            ),
+            CustomError::DataChannelError(_) => String::from(
+                "Your message was not sent, you are not connected to your friend.",
+            ),
+            CustomError::MediaError(reason) => {
+                format!("Could not use the camera, {}. Check the permissions of this page.", reason)
+            }
+            CustomError::StorageError(_) => String::from(
+                "Could not save your data, the storage of this browser is full or disabled.",
+            ),
+            CustomError::SettingsError(reason) => format!("These settings cannot be saved, {}.", reason),
+            CustomError::JsError(reason) => format!("Something went wrong: {}", reason),
+        }
+    }
+}
+
+impl Display for CustomError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            CustomError::SignalingError(reason) => write!(f, "Signaling error: {}", reason),
+            CustomError::InputTypeError(reason) => write!(f, "Unexpected input: {}", reason),
+            CustomError::JsonParseError(error) => write!(f, "Invalid JSON: {}", error),
+            CustomError::SessionCodeError(reason) => write!(f, "Invalid session code: {}", reason),
+            CustomError::RoomCodeError(reason) => write!(f, "Invalid room code: {}", reason),
+            CustomError::QrCodeError(reason) => write!(f, "QR code error: {}", reason),
+            CustomError::NegotiationError(reason) => write!(f, "Negotiation error: {}", reason),
+            CustomError::SdpParseError { line, reason } => write!(f, "Invalid SDP at line {}: {}", line, reason),
+            CustomError::SdpValidationError(reason) => write!(f, "Unusable SDP: {}", reason),
+            CustomError::IceError(reason) => write!(f, "ICE error: {}", reason),
+            CustomError::CandidateParseError(reason) => write!(f, "Invalid ICE candidate: {}", reason),
+            CustomError::ConnectionLostError(reason) => write!(f, "Connection lost: {}", reason),
+            CustomError::DataChannelError(reason) => write!(f, "Data channel error: {}", reason),
+            CustomError::MediaError(reason) => write!(f, "Media error: {}", reason),
+            CustomError::StorageError(reason) => write!(f, "Storage error: {}", reason),
+            CustomError::SettingsError(reason) => write!(f, "Invalid settings: {}", reason),
+            CustomError::JsError(reason) => write!(f, "JavaScript error: {}", reason),
+        }
+    }
+}
+
+impl Error for CustomError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CustomError::JsonParseError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for CustomError {
+    fn from(error: serde_json::Error) -> Self {
+        CustomError::JsonParseError(error)
+    }
+}
+
+impl From<JsValue> for CustomError {
+    fn from(value: JsValue) -> Self {
+        CustomError::JsError(js_error_message(&value))
+    }
+}
+
+/// Readable description of a rejected promise or a thrown exception, usually an `Error` or a `DOMException`
+pub fn js_error_message(value: &JsValue) -> String {
+    match value.dyn_ref::<js_sys::Error>() {
+        Some(error) => format!("{}: {}", String::from(error.name()), String::from(error.message())),
+        None => value.as_string().unwrap_or_else(|| format!("{:?}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_the_details_for_the_logs() {
+        let error = CustomError::SdpParseError {
+            line: 3,
+            reason: String::from("Expected <type>=<value>"),
+        };
+        assert_eq!(error.to_string(), "Invalid SDP at line 3: Expected <type>=<value>");
+    }
+
+    #[test]
+    fn keeps_the_json_error_as_source() {
+        let error = CustomError::from(serde_json::from_str::<u8>("{").unwrap_err());
+        assert!(error.source().is_some());
+        assert!(CustomError::IceError(String::from("failed")).source().is_none());
+    }
+
+    #[test]
+    fn tells_the_user_what_to_do() {
+        let error = CustomError::SessionCodeError(String::from("it is not an answer"));
+        assert_eq!(
+            error.user_message(),
+            "This code is invalid, it is not an answer. Ask your friend to send it again."
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::error::CustomError;
+
+// https://tools.ietf.org/html/rfc8839#section-5.1
+// candidate:<foundation> <component> <transport> <priority> <address> <port> typ <type> [raddr <address>] [rport <port>] *(<key> <value>)
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CandidateTransport {
+    Udp,
+    Tcp,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CandidateType {
+    Host,
+    ServerReflexive,
+    PeerReflexive,
+    Relay,
+}
+
+/// A parsed ICE candidate line, as found in `RTCIceCandidate.candidate` or in an SDP `a=candidate` attribute.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IceCandidate {
+    pub foundation: String,
+    pub component: u16,
+    pub transport: CandidateTransport,
+    pub priority: u32,
+    pub address: String,
+    pub port: u16,
+    pub candidate_type: CandidateType,
+    pub related_address: Option<String>,
+    pub related_port: Option<u16>,
+    pub tcp_type: Option<String>,
+    /// Browser specific attributes (generation, ufrag, network-id...), kept in order
+    pub extensions: Vec<(String, String)>,
+}
+
+impl IceCandidate {
+    /// Host candidates obfuscated by the browser use a random `.local` mDNS name
+    pub fn is_mdns(&self) -> bool {
+        self.address.ends_with(".local")
+    }
+}
+
+impl Display for CandidateTransport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            CandidateTransport::Udp => write!(f, "udp"),
+            CandidateTransport::Tcp => write!(f, "tcp"),
+        }
+    }
+}
+
+impl FromStr for CandidateTransport {
+    type Err = CustomError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "udp" => Ok(CandidateTransport::Udp),
+            "tcp" => Ok(CandidateTransport::Tcp),
+            _ => Err(CustomError::CandidateParseError(format!("Unknown transport {}", value))),
+        }
+    }
+}
+
+impl Display for CandidateType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            CandidateType::Host => write!(f, "host"),
+            CandidateType::ServerReflexive => write!(f, "srflx"),
+            CandidateType::PeerReflexive => write!(f, "prflx"),
+            CandidateType::Relay => write!(f, "relay"),
+        }
+    }
+}
+
+impl FromStr for CandidateType {
+    type Err = CustomError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "host" => Ok(CandidateType::Host),
+            "srflx" => Ok(CandidateType::ServerReflexive),
+            "prflx" => Ok(CandidateType::PeerReflexive),
+            "relay" => Ok(CandidateType::Relay),
+            _ => Err(CustomError::CandidateParseError(format!("Unknown candidate type {}", value))),
+        }
+    }
+}
+
+fn parse_field<T: FromStr>(value: Option<&str>, field: &str) -> Result<T, CustomError> {
+    value
+        .ok_or_else(|| CustomError::CandidateParseError(format!("Missing {}", field)))?
+        .parse::<T>()
+        .map_err(|_| CustomError::CandidateParseError(format!("Invalid {}", field)))
+}
+
+impl FromStr for IceCandidate {
+    type Err = CustomError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let line = line.strip_prefix("a=").unwrap_or(line);
+        let line = line.strip_prefix("candidate:").ok_or_else(|| {
+            CustomError::CandidateParseError(String::from("Missing candidate: prefix"))
+        })?;
+
+        let mut tokens = line.split_whitespace();
+        let foundation = parse_field::<String>(tokens.next(), "foundation")?;
+        let component = parse_field(tokens.next(), "component")?;
+        let transport = parse_field(tokens.next(), "transport")?;
+        let priority = parse_field(tokens.next(), "priority")?;
+        let address = parse_field::<String>(tokens.next(), "address")?;
+        let port = parse_field(tokens.next(), "port")?;
+        if tokens.next() != Some("typ") {
+            return Err(CustomError::CandidateParseError(String::from("Missing typ")));
+        }
+        let candidate_type = parse_field(tokens.next(), "candidate type")?;
+
+        let mut candidate = IceCandidate {
+            foundation,
+            component,
+            transport,
+            priority,
+            address,
+            port,
+            candidate_type,
+            related_address: None,
+            related_port: None,
+            tcp_type: None,
+            extensions: vec![],
+        };
+        while let Some(key) = tokens.next() {
+            let value = tokens.next().ok_or_else(|| {
+                CustomError::CandidateParseError(format!("Missing value for {}", key))
+            })?;
+            match key {
+                "raddr" => candidate.related_address = Some(value.to_string()),
+                "rport" => candidate.related_port = Some(parse_field(Some(value), "rport")?),
+                "tcptype" => candidate.tcp_type = Some(value.to_string()),
+                _ => candidate.extensions.push((key.to_string(), value.to_string())),
+            }
+        }
+        Ok(candidate)
+    }
+}
+
+impl Display for IceCandidate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "candidate:{} {} {} {} {} {} typ {}",
+            self.foundation,
+            self.component,
+            self.transport,
+            self.priority,
+            self.address,
+            self.port,
+            self.candidate_type
+        )?;
+        if let Some(related_address) = &self.related_address {
+            write!(f, " raddr {}", related_address)?;
+        }
+        if let Some(related_port) = self.related_port {
+            write!(f, " rport {}", related_port)?;
+        }
+        if let Some(tcp_type) = &self.tcp_type {
+            write!(f, " tcptype {}", tcp_type)?;
+        }
+        for (key, value) in &self.extensions {
+            write!(f, " {} {}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+// Written instead of the local addresses
+const HIDDEN_ADDRESS: &str = "0.0.0.0";
+const HIDDEN_PORT: &str = "9";
+
+// Replaces the `<address type> <address>` at the end of a line, `IN IP4 0.0.0.0` as in trickle ICE offers
+fn hide_trailing_address(tokens: &mut [&str]) {
+    let length = tokens.len();
+    // The network type is glued to the prefix of the `c=` lines
+    if length >= 3 && (tokens[length - 3] == "IN" || tokens[length - 3] == "c=IN") {
+        tokens[length - 2] = "IP4";
+        tokens[length - 1] = HIDDEN_ADDRESS;
+    }
+}
+
+fn hide_default_address(line: &str) -> String {
+    let mut tokens = line.split(' ').collect::<Vec<&str>>();
+    if line.starts_with("c=") || line.starts_with("o=") {
+        hide_trailing_address(&mut tokens);
+    } else if line.starts_with("m=") && tokens.len() > 1 && tokens[1] != "0" {
+        // A port of 0 rejects the media, it is kept
+        tokens[1] = HIDDEN_PORT;
+    } else if line.starts_with("a=rtcp:") {
+        tokens[0] = "a=rtcp:9";
+        hide_trailing_address(&mut tokens);
+    } else {
+        return line.to_string();
+    }
+    tokens.join(" ")
+}
+
+/// Which local candidates are shared with the other peer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum CandidatePolicy {
+    #[default]
+    All,
+    /// Hides the local network addresses
+    NoHost,
+    /// Hides every address of the user, requires a TURN server
+    RelayOnly,
+}
+
+impl CandidatePolicy {
+    pub const ALL: [CandidatePolicy; 3] = [
+        CandidatePolicy::All,
+        CandidatePolicy::NoHost,
+        CandidatePolicy::RelayOnly,
+    ];
+
+    pub fn allows(&self, candidate: &IceCandidate) -> bool {
+        match self {
+            CandidatePolicy::All => true,
+            CandidatePolicy::NoHost => candidate.candidate_type != CandidateType::Host,
+            CandidatePolicy::RelayOnly => candidate.candidate_type == CandidateType::Relay,
+        }
+    }
+
+    /// The candidate as it can be shared, `None` when it is hidden.
+    /// Like the browsers, the base of a reflexive candidate is blanked, it is the local address.
+    pub fn apply(&self, mut candidate: IceCandidate) -> Option<IceCandidate> {
+        if !self.allows(&candidate) {
+            return None;
+        }
+        if *self != CandidatePolicy::All {
+            if candidate.related_address.is_some() {
+                candidate.related_address = Some(String::from(HIDDEN_ADDRESS));
+            }
+            if candidate.related_port.is_some() {
+                candidate.related_port = Some(0);
+            }
+        }
+        Some(candidate)
+    }
+
+    /// Unreadable candidates are only forwarded when nothing has to be hidden
+    pub fn apply_line(&self, line: &str) -> Option<String> {
+        if *self == CandidatePolicy::All {
+            return Some(line.to_string());
+        }
+        match line.parse::<IceCandidate>() {
+            Ok(candidate) => self.apply(candidate).map(|candidate| candidate.to_string()),
+            Err(err) => {
+                log::warn!("Could not parse ICE candidate {:?}: {:?}", line, err);
+                None
+            }
+        }
+    }
+
+    /// Removes or rewrites the `a=candidate` lines of an SDP that this policy does not allow.
+    /// The default candidate of the `c=`, `m=` and `a=rtcp` lines, and the address of the `o=` line,
+    /// may be a local one: they become the placeholders the browsers use with trickle ICE.
+    pub fn filter_sdp(&self, sdp: &str) -> String {
+        if *self == CandidatePolicy::All {
+            return sdp.to_string();
+        }
+        sdp.split_terminator("\r\n")
+            .filter_map(|line| {
+                if line.starts_with("a=candidate:") {
+                    self.apply_line(line).map(|candidate| format!("a={}", candidate))
+                } else {
+                    Some(hide_default_address(line))
+                }
+            })
+            .map(|line| format!("{}\r\n", line))
+            .collect()
+    }
+
+    pub fn value(&self) -> &'static str {
+        match self {
+            CandidatePolicy::All => "all",
+            CandidatePolicy::NoHost => "no-host",
+            CandidatePolicy::RelayOnly => "relay-only",
+        }
+    }
+
+    pub fn from_value(value: &str) -> Option<Self> {
+        CandidatePolicy::ALL
+            .iter()
+            .find(|policy| policy.value() == value)
+            .copied()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CandidatePolicy::All => "Share every address",
+            CandidatePolicy::NoHost => "Hide my local addresses",
+            CandidatePolicy::RelayOnly => "Relay only (needs a TURN server)",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOST: &str = "candidate:1467250027 1 udp 2122260223 192.168.1.20 56143 typ host generation 0 ufrag sXS6 network-id 1";
+    const SRFLX: &str =
+        "candidate:842163049 1 udp 1677729535 203.0.113.7 56143 typ srflx raddr 192.168.1.20 rport 56143 generation 0";
+    const RELAY: &str = "candidate:3745744979 1 udp 41885439 198.51.100.3 60001 typ relay raddr 203.0.113.7 rport 56143";
+    const TCP_HOST: &str = "a=candidate:1 1 TCP 2105458943 192.168.1.20 9 typ host tcptype active";
+
+    const OFFER: &str = "v=0\r\n\
+o=- 4215775240449105457 2 IN IP4 192.168.1.20\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=application 56143 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+c=IN IP4 192.168.1.20\r\n\
+a=rtcp:56143 IN IP4 192.168.1.20\r\n\
+a=rtcp-mux\r\n\
+a=candidate:1467250027 1 udp 2122260223 192.168.1.20 56143 typ host generation 0\r\n\
+a=candidate:842163049 1 udp 1677729535 203.0.113.7 56143 typ srflx raddr 192.168.1.20 rport 56143 generation 0\r\n\
+a=candidate:3745744979 1 udp 41885439 198.51.100.3 60001 typ relay raddr 203.0.113.7 rport 56143\r\n\
+a=ice-ufrag:sXS6\r\n\
+a=mid:0\r\n\
+a=sctp-port:5000\r\n";
+
+    #[test]
+    fn parses_host_reflexive_and_relay_candidates() {
+        let host: IceCandidate = HOST.parse().unwrap();
+        assert_eq!(host.candidate_type, CandidateType::Host);
+        assert_eq!(host.address, "192.168.1.20");
+        assert_eq!(host.port, 56143);
+        assert_eq!(host.related_address, None);
+        assert_eq!(host.related_port, None);
+        assert_eq!(
+            host.extensions,
+            vec![
+                (String::from("generation"), String::from("0")),
+                (String::from("ufrag"), String::from("sXS6")),
+                (String::from("network-id"), String::from("1")),
+            ]
+        );
+
+        let srflx: IceCandidate = SRFLX.parse().unwrap();
+        assert_eq!(srflx.candidate_type, CandidateType::ServerReflexive);
+        assert_eq!(srflx.related_address.as_deref(), Some("192.168.1.20"));
+        assert_eq!(srflx.related_port, Some(56143));
+
+        let relay: IceCandidate = RELAY.parse().unwrap();
+        assert_eq!(relay.candidate_type, CandidateType::Relay);
+        assert_eq!(relay.priority, 41885439);
+        assert!(relay.extensions.is_empty());
+
+        let tcp: IceCandidate = TCP_HOST.parse().unwrap();
+        assert_eq!(tcp.transport, CandidateTransport::Tcp);
+        assert_eq!(tcp.tcp_type.as_deref(), Some("active"));
+    }
+
+    #[test]
+    fn writes_back_what_it_parsed() {
+        for line in &[HOST, SRFLX, RELAY] {
+            let candidate: IceCandidate = line.parse().unwrap();
+            assert_eq!(candidate.to_string(), *line);
+        }
+        // The transport is written in lowercase
+        let tcp: IceCandidate = TCP_HOST.parse().unwrap();
+        assert_eq!(tcp.to_string().parse::<IceCandidate>().unwrap(), tcp);
+    }
+
+    #[test]
+    fn refuses_malformed_lines() {
+        assert!("".parse::<IceCandidate>().is_err());
+        assert!("1 1 udp 2122260223 192.168.1.20 56143 typ host".parse::<IceCandidate>().is_err());
+        assert!("candidate:1 1 udp 2122260223 192.168.1.20".parse::<IceCandidate>().is_err());
+        assert!("candidate:1 1 sctp 2122260223 192.168.1.20 56143 typ host".parse::<IceCandidate>().is_err());
+        assert!("candidate:1 1 udp 2122260223 192.168.1.20 99999 typ host".parse::<IceCandidate>().is_err());
+        assert!("candidate:1 1 udp 2122260223 192.168.1.20 56143 type host".parse::<IceCandidate>().is_err());
+        assert!("candidate:1 1 udp 2122260223 192.168.1.20 56143 typ local".parse::<IceCandidate>().is_err());
+        assert!("candidate:1 1 udp 2122260223 192.168.1.20 56143 typ host generation".parse::<IceCandidate>().is_err());
+        assert!("candidate:1 1 udp 1 203.0.113.7 1 typ srflx raddr 0.0.0.0 rport x".parse::<IceCandidate>().is_err());
+    }
+
+    #[test]
+    fn keeps_the_candidates_each_policy_allows() {
+        let kept = |policy: CandidatePolicy| {
+            [HOST, SRFLX, RELAY]
+                .iter()
+                .map(|line| policy.allows(&line.parse().unwrap()))
+                .collect::<Vec<bool>>()
+        };
+        assert_eq!(kept(CandidatePolicy::All), vec![true, true, true]);
+        assert_eq!(kept(CandidatePolicy::NoHost), vec![false, true, true]);
+        assert_eq!(kept(CandidatePolicy::RelayOnly), vec![false, false, true]);
+
+        assert_eq!(CandidatePolicy::All.apply_line("garbage").as_deref(), Some("garbage"));
+        assert_eq!(CandidatePolicy::NoHost.apply_line("garbage"), None);
+        assert_eq!(CandidatePolicy::NoHost.apply_line(HOST), None);
+    }
+
+    #[test]
+    fn hides_the_local_base_of_the_reflexive_candidates() {
+        assert_eq!(CandidatePolicy::All.apply_line(SRFLX).as_deref(), Some(SRFLX));
+
+        let srflx: IceCandidate = CandidatePolicy::NoHost.apply_line(SRFLX).unwrap().parse().unwrap();
+        assert_eq!(srflx.address, "203.0.113.7");
+        assert_eq!(srflx.related_address.as_deref(), Some("0.0.0.0"));
+        assert_eq!(srflx.related_port, Some(0));
+
+        let relay = CandidatePolicy::RelayOnly.apply(RELAY.parse().unwrap()).unwrap();
+        assert_eq!(relay.related_address.as_deref(), Some("0.0.0.0"));
+        assert_eq!(relay.related_port, Some(0));
+    }
+
+    #[test]
+    fn filters_the_candidates_and_the_default_address_of_an_sdp() {
+        assert_eq!(CandidatePolicy::All.filter_sdp(OFFER), OFFER);
+
+        let filtered = CandidatePolicy::NoHost.filter_sdp(OFFER);
+        assert!(!filtered.contains("192.168.1.20"));
+        assert!(!filtered.contains("typ host"));
+        assert!(filtered.contains("typ srflx raddr 0.0.0.0 rport 0"));
+        assert!(filtered.contains("typ relay"));
+        assert!(filtered.contains("o=- 4215775240449105457 2 IN IP4 0.0.0.0\r\n"));
+        assert!(filtered.contains("m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n"));
+        assert!(filtered.contains("c=IN IP4 0.0.0.0\r\n"));
+        assert!(filtered.contains("a=rtcp:9 IN IP4 0.0.0.0\r\n"));
+        assert!(filtered.contains("a=rtcp-mux\r\n"));
+        assert!(filtered.ends_with("a=sctp-port:5000\r\n"));
+
+        let filtered = CandidatePolicy::RelayOnly.filter_sdp(OFFER);
+        assert!(!filtered.contains("203.0.113.7"));
+        assert!(!filtered.contains("typ srflx"));
+        assert!(filtered.contains("198.51.100.3 60001 typ relay raddr 0.0.0.0 rport 0"));
+    }
+}
@@ -1,2 +1,19 @@
+pub mod callbacks;
+pub mod clipboard;
+pub mod connection_state;
+pub mod contacts;
+pub mod error;
+pub mod ice_candidate;
 pub mod participants;
-pub mod socket;
\ No newline at end of file
+pub mod qr_code;
+pub mod query;
+pub mod room_code;
+mod room_words;
+pub mod route;
+pub mod sdp;
+pub mod session_code;
+pub mod settings;
+pub mod socket;
+pub mod stats;
+pub mod storage;
+pub mod theme;
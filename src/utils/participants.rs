@@ -4,6 +4,10 @@ use serde::{Deserialize, Serialize};
 pub struct Participants {
     #[serde(rename = "userFrom")]
     pub user_from: String,
+    /// Empty in a shared room, the name of the friend is not known
     #[serde(rename = "userTo")]
     pub user_to: String,
+    /// Set when joining with an invite link, the server puts everyone using it in the same room
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
 }
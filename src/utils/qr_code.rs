@@ -0,0 +1,85 @@
+use qrcode::{Color, EcLevel, QrCode};
+
+use crate::utils::error::CustomError;
+
+// Light modules around the code, required by scanners
+const QUIET_ZONE: usize = 4;
+
+/// Modules of a QR code, row by row, `true` being dark
+pub struct QrMatrix {
+    width: usize,
+    modules: Vec<bool>,
+}
+
+impl QrMatrix {
+    /// Session codes are long, the lowest error correction keeps the code scannable on a phone
+    pub fn encode(data: &str) -> Result<QrMatrix, CustomError> {
+        let code = QrCode::with_error_correction_level(data.as_bytes(), EcLevel::L)
+            .map_err(|err| CustomError::QrCodeError(err.to_string()))?;
+        Ok(QrMatrix {
+            width: code.width(),
+            modules: code.to_colors().into_iter().map(|color| color == Color::Dark).collect(),
+        })
+    }
+
+    /// Side of the code, quiet zone included, in modules
+    pub fn size(&self) -> usize {
+        self.width + 2 * QUIET_ZONE
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.width && self.modules[y * self.width + x]
+    }
+
+    /// SVG path drawing every dark module, meant for a `viewBox` of `size()` by `size()`.
+    /// Horizontal runs of dark modules share a single rectangle to keep the path short.
+    pub fn svg_path(&self) -> String {
+        let mut path = String::new();
+        for y in 0..self.width {
+            let mut x = 0;
+            while x < self.width {
+                if !self.is_dark(x, y) {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < self.width && self.is_dark(x, y) {
+                    x += 1;
+                }
+                path.push_str(&format!("M{},{}h{}v1h-{}z", start + QUIET_ZONE, y + QUIET_ZONE, x - start, x - start));
+            }
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_a_session_code() {
+        let matrix = QrMatrix::encode("eJyNkE1rwzAMhv-K0TmH2I7z4dvYaQx22mFQSnEcpfVqW8FRWErpf59dGOwyGOgivdL7SLqBXbwNZ8cWDFgWvBqYjNaasZVw6SoHqfNK_wP3vl-Lgp0JwSP5A").unwrap();
+        // Version 3 or more, never the 21 modules of version 1
+        assert!(matrix.size() > 21 + 2 * QUIET_ZONE);
+        // Finder pattern in the top left corner
+        assert!(matrix.is_dark(0, 0));
+        assert!(matrix.is_dark(6, 6));
+        assert!(!matrix.is_dark(7, 7));
+    }
+
+    #[test]
+    fn merges_dark_runs() {
+        let matrix = QrMatrix {
+            width: 3,
+            modules: vec![true, true, false, false, false, false, false, true, true],
+        };
+        assert_eq!(matrix.svg_path(), "M4,4h2v1h-2zM5,6h2v1h-2z");
+    }
+
+    #[test]
+    fn rejects_data_too_long_for_a_qr_code() {
+        let data = "a".repeat(5_000);
+        assert!(matches!(QrMatrix::encode(&data), Err(CustomError::QrCodeError(_))));
+    }
+}
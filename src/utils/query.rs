@@ -0,0 +1,29 @@
+/// Value of the `name` parameter of a query string such as `?debug=true&signaling=broadcast`.
+/// A parameter without value, `?demo` for instance, has an empty value.
+pub fn query_parameter<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .trim_start_matches('?')
+        .split('&')
+        .filter_map(|parameter| {
+            let mut parts = parameter.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), value) if key == name => Some(value.unwrap_or_default()),
+                _ => None,
+            }
+        })
+        .next_back()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_parameters() {
+        assert_eq!(query_parameter("?debug=true&signaling=broadcast", "signaling"), Some("broadcast"));
+        assert_eq!(query_parameter("?demo", "demo"), Some(""));
+        assert_eq!(query_parameter("?demo=1&demo=2", "demo"), Some("2"));
+        assert_eq!(query_parameter("?signaling=broadcast", "demo"), None);
+        assert_eq!(query_parameter("", "demo"), None);
+    }
+}
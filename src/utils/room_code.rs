@@ -0,0 +1,119 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use crate::utils::error::CustomError;
+use crate::utils::room_words::{ADJECTIVES, ANIMALS};
+
+// 8 bits per word and 13 for the number, about 37 bits
+const NUMBERS: u16 = 10_000;
+
+/// Name of a room which can be read aloud, "purple-quiet-otter-4271" for instance.
+/// It is the room identifier itself, the links and the codes lead to the same rooms.
+/// Anyone who knows it joins, its 168 billion values are what keeps the room from being guessed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RoomCode {
+    adjectives: [&'static str; 2],
+    animal: &'static str,
+    number: u16,
+}
+
+impl RoomCode {
+    pub fn from_random_bytes(bytes: [u8; 5]) -> Self {
+        Self {
+            adjectives: [ADJECTIVES[bytes[0] as usize], ADJECTIVES[bytes[1] as usize]],
+            animal: ANIMALS[bytes[2] as usize],
+            // The modulo slightly favors the first numbers, well below a bit of entropy
+            number: u16::from_le_bytes([bytes[3], bytes[4]]) % NUMBERS,
+        }
+    }
+
+    pub fn generate() -> Result<Self, CustomError> {
+        let crypto = web_sys::window()
+            .ok_or_else(|| CustomError::JsError(String::from("No window")))?
+            .crypto()?;
+        let mut bytes = [0u8; 5];
+        crypto.get_random_values_with_u8_array(&mut bytes)?;
+        Ok(RoomCode::from_random_bytes(bytes))
+    }
+}
+
+fn find_word(words: &[&'static str], word: &str) -> Option<&'static str> {
+    // The lists are sorted
+    words.binary_search(&word).ok().map(|index| words[index])
+}
+
+impl FromStr for RoomCode {
+    type Err = CustomError;
+
+    /// Accepts the codes as people type them: "Purple Quiet Otter 4271", "purple_quiet-otter-4271 "...
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        let code = code.to_lowercase();
+        let parts: Vec<&str> = code
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect();
+        let (first, second, animal, number) = match parts.as_slice() {
+            [first, second, animal, number] => (*first, *second, *animal, *number),
+            _ => return Err(CustomError::RoomCodeError(String::from("it must have four parts"))),
+        };
+        let invalid_word = |word: &str| CustomError::RoomCodeError(format!("\"{}\" is not one of its words", word));
+        Ok(RoomCode {
+            adjectives: [
+                find_word(&ADJECTIVES, first).ok_or_else(|| invalid_word(first))?,
+                find_word(&ADJECTIVES, second).ok_or_else(|| invalid_word(second))?,
+            ],
+            animal: find_word(&ANIMALS, animal).ok_or_else(|| invalid_word(animal))?,
+            number: number
+                .parse::<u16>()
+                .ok()
+                .filter(|number| *number < NUMBERS)
+                .ok_or_else(|| CustomError::RoomCodeError(format!("it must end with a number below {}", NUMBERS)))?,
+        })
+    }
+}
+
+impl Display for RoomCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}-{}-{}-{}", self.adjectives[0], self.adjectives[1], self.animal, self.number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::route::is_valid_room_id;
+
+    #[test]
+    fn word_lists_are_sorted_and_unique() {
+        for words in &[&ADJECTIVES, &ANIMALS] {
+            assert!(words.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(words.iter().all(|word| word.chars().all(|c| c.is_ascii_lowercase())));
+        }
+    }
+
+    #[test]
+    fn normalizes_what_people_type() {
+        let code = RoomCode::from_random_bytes([0, 1, 0, 42, 0]);
+        let second = ADJECTIVES[1];
+        assert_eq!(code.to_string(), format!("able-{}-aardvark-42", second));
+        assert_eq!(format!("  Able {} Aardvark 42 ", second.to_uppercase()).parse::<RoomCode>().unwrap(), code);
+        assert_eq!(format!("able_{}-aardvark--042", second).parse::<RoomCode>().unwrap(), code);
+    }
+
+    #[test]
+    fn rejects_unknown_codes() {
+        assert!("able-aardvark-42".parse::<RoomCode>().is_err());
+        assert!("able-able-unicorn-42".parse::<RoomCode>().is_err());
+        assert!("able-able-aardvark-10000".parse::<RoomCode>().is_err());
+        assert!("able-able-aardvark-42-1".parse::<RoomCode>().is_err());
+    }
+
+    #[test]
+    fn codes_are_room_identifiers() {
+        for bytes in &[[0, 0, 0, 0, 0], [255, 255, 255, 255, 255], [17, 200, 3, 9, 120]] {
+            let code = RoomCode::from_random_bytes(*bytes);
+            assert!(is_valid_room_id(&code.to_string()));
+            assert_eq!(code.to_string().parse::<RoomCode>().unwrap(), code);
+        }
+    }
+}
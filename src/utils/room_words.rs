@@ -0,0 +1,61 @@
+// Short, common and easy to spell words, read aloud in the room codes
+
+pub const ADJECTIVES: [&str; 256] = [
+    "able", "acid", "agile", "airy", "alert", "alive", "amber", "ample", "amused", "antique", "apt",
+    "arctic", "ashen", "autumn", "avid", "awake", "azure", "baggy", "bald", "balmy", "bashful",
+    "beige", "best", "big", "black", "bland", "blank", "blond", "blue", "blunt", "bold", "bony",
+    "bossy", "bouncy", "brave", "breezy", "brief", "bright", "brisk", "broad", "bronze", "brown",
+    "bubbly", "bumpy", "busy", "calm", "candid", "carefree", "careful", "casual", "cheeky",
+    "cheerful", "chilly", "chirpy", "chosen", "chubby", "civil", "classic", "clean", "clear",
+    "clever", "close", "cloudy", "clumsy", "coastal", "cold", "comfy", "cool", "copper", "coral",
+    "cosmic", "cosy", "crafty", "creamy", "crisp", "crunchy", "cuddly", "curly", "curvy", "cute",
+    "daily", "damp", "dapper", "daring", "dark", "dear", "decent", "deep", "dizzy", "dotty",
+    "dreamy", "dry", "dusty", "eager", "early", "eastern", "easy", "elated", "electric", "elegant",
+    "empty", "epic", "equal", "even", "exact", "exotic", "extra", "fabulous", "fair", "fancy",
+    "far", "fast", "fearless", "festive", "fierce", "fine", "firm", "first", "flat", "fluffy",
+    "fond", "formal", "fresh", "friendly", "frosty", "funny", "fuzzy", "gentle", "giant", "giddy",
+    "gifted", "glad", "glossy", "golden", "good", "grand", "grassy", "gray", "great", "green",
+    "gritty", "grumpy", "happy", "hardy", "hasty", "hazy", "healthy", "hearty", "heavy", "helpful",
+    "hidden", "high", "hollow", "honest", "hopeful", "huge", "humble", "hungry", "icy", "ideal",
+    "idle", "indigo", "inner", "ivory", "jade", "jazzy", "jolly", "jumbo", "jumpy", "just", "keen",
+    "kind", "kingly", "large", "last", "late", "lazy", "leafy", "lean", "lemon", "level", "light",
+    "likely", "lilac", "lime", "little", "lively", "local", "lofty", "lone", "long", "loud",
+    "lovely", "loyal", "lucky", "lunar", "lush", "magic", "major", "maroon", "mellow", "merry",
+    "mighty", "mild", "milky", "mint", "misty", "modern", "modest", "moody", "mossy", "muddy",
+    "musical", "mystic", "narrow", "native", "neat", "neon", "new", "next", "nice", "nifty",
+    "nimble", "noble", "noisy", "normal", "northern", "novel", "nutty", "oak", "odd", "olive",
+    "orange", "orchid", "outer", "oval", "pale", "peachy", "perky", "pink", "plain", "playful",
+    "plucky", "plump", "polite", "posh", "proud", "purple", "quick", "quiet", "quirky", "rainy",
+    "rapid", "rare", "ready", "real",
+];
+
+pub const ANIMALS: [&str; 256] = [
+    "aardvark", "albatross", "alligator", "alpaca", "anchovy", "ant", "anteater", "antelope", "ape",
+    "armadillo", "baboon", "badger", "barracuda", "bat", "bear", "beaver", "bee", "beetle", "bison",
+    "boar", "bobcat", "buffalo", "bull", "bunny", "butterfly", "buzzard", "camel", "canary",
+    "capybara", "cardinal", "caribou", "carp", "cat", "caterpillar", "catfish", "cattle",
+    "chameleon", "cheetah", "chicken", "chimp", "chinchilla", "chipmunk", "clam", "cobra",
+    "cockatoo", "cod", "condor", "cougar", "cow", "coyote", "crab", "crane", "crayfish", "cricket",
+    "crocodile", "crow", "cuckoo", "dachshund", "deer", "dingo", "dodo", "dog", "dolphin", "donkey",
+    "dove", "dragonfly", "duck", "eagle", "eel", "egret", "elephant", "elk", "emu", "falcon",
+    "ferret", "finch", "firefly", "flamingo", "flounder", "fly", "fox", "frog", "gazelle", "gecko",
+    "gerbil", "gibbon", "giraffe", "gnu", "goat", "goldfish", "goose", "gopher", "gorilla",
+    "grasshopper", "grouse", "gull", "guppy", "haddock", "halibut", "hamster", "hare", "hawk",
+    "hedgehog", "heron", "herring", "hippo", "hornet", "horse", "hound", "hummingbird", "husky",
+    "hyena", "ibex", "ibis", "iguana", "impala", "jackal", "jaguar", "jay", "jellyfish", "kangaroo",
+    "kestrel", "kingfisher", "kitten", "kiwi", "koala", "krill", "ladybug", "lamb", "lark",
+    "lemming", "lemur", "leopard", "lion", "lizard", "llama", "lobster", "locust", "loon", "lynx",
+    "macaw", "magpie", "mallard", "mammoth", "manatee", "mandrill", "marlin", "marmot", "meerkat",
+    "mink", "mole", "mongoose", "monkey", "moose", "mosquito", "moth", "mouse", "mule", "mussel",
+    "narwhal", "newt", "nightingale", "ocelot", "octopus", "okapi", "opossum", "orca", "oriole",
+    "osprey", "ostrich", "otter", "owl", "ox", "oyster", "panda", "panther", "parrot", "partridge",
+    "peacock", "pelican", "penguin", "perch", "pheasant", "pig", "pigeon", "pike", "piranha",
+    "platypus", "pony", "poodle", "porcupine", "porpoise", "possum", "prawn", "puffin", "puma",
+    "python", "quail", "rabbit", "raccoon", "ram", "rat", "raven", "reindeer", "rhino", "robin",
+    "rooster", "salamander", "salmon", "sardine", "scorpion", "seahorse", "seal", "shark", "sheep",
+    "shrimp", "skunk", "sloth", "slug", "snail", "snake", "sparrow", "spider", "squid", "squirrel",
+    "starfish", "stingray", "stork", "swallow", "swan", "swift", "tapir", "tarantula", "termite",
+    "tiger", "toad", "tortoise", "toucan", "trout", "tuna", "turkey", "turtle", "viper", "vulture",
+    "wallaby", "walrus", "wasp", "weasel", "whale", "wolf", "wombat", "woodpecker", "worm", "wren",
+    "yak", "zebra",
+];
@@ -0,0 +1,98 @@
+use wasm_bindgen::JsValue;
+
+const ROOM_PREFIX: &str = "#/room/";
+const MAX_ROOM_ID_LENGTH: usize = 64;
+
+/// Where the user is, kept in the hash of the URL so the links can be shared
+#[derive(Clone, Debug, PartialEq)]
+pub enum Route {
+    Home,
+    Room(String),
+}
+
+impl Route {
+    /// Reads a `location.hash` such as `#/room/<id>`, anything else leads home
+    pub fn from_hash(hash: &str) -> Self {
+        match hash.strip_prefix(ROOM_PREFIX).map(|id| id.trim_end_matches('/')) {
+            Some(id) if is_valid_room_id(id) => Route::Room(id.to_string()),
+            _ => Route::Home,
+        }
+    }
+
+    pub fn to_hash(&self) -> String {
+        match self {
+            Route::Home => String::from("#/"),
+            Route::Room(id) => format!("{}{}", ROOM_PREFIX, id),
+        }
+    }
+
+    pub fn room(&self) -> Option<&str> {
+        match self {
+            Route::Home => None,
+            Route::Room(id) => Some(id),
+        }
+    }
+
+    pub fn current() -> Self {
+        web_sys::window()
+            .and_then(|window| window.location().hash().ok())
+            .map_or(Route::Home, |hash| Route::from_hash(&hash))
+    }
+
+    /// Adds an entry to the history, a `hashchange` event follows
+    pub fn navigate(&self) {
+        if let Some(window) = web_sys::window() {
+            if let Err(err) = window.location().set_hash(&self.to_hash()) {
+                log::error!("Could not navigate to {}: {:?}", self.to_hash(), err);
+            }
+        }
+    }
+
+    /// Changes the URL without history entry, and without `hashchange` event
+    pub fn replace(&self) {
+        let result = web_sys::window()
+            .map(|window| window.history())
+            .map(|history| history?.replace_state_with_url(&JsValue::NULL, "", Some(&self.to_hash())));
+        if let Some(Err(err)) = result {
+            log::error!("Could not replace the URL with {}: {:?}", self.to_hash(), err);
+        }
+    }
+}
+
+/// The ids go in URLs and in the signaling messages, they are kept plain
+pub fn is_valid_room_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_ROOM_ID_LENGTH
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The current page, opened in the room
+pub fn invite_link(room: &str) -> Option<String> {
+    let location = web_sys::window()?.location();
+    let href = location.href().ok()?;
+    let page = href.split('#').next().unwrap_or_default();
+    Some(format!("{}{}", page, Route::Room(room.to_string()).to_hash()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_room_of_the_hash() {
+        assert_eq!(Route::from_hash("#/room/4f2a-b"), Route::Room(String::from("4f2a-b")));
+        assert_eq!(Route::from_hash("#/room/4f2a/"), Route::Room(String::from("4f2a")));
+        assert_eq!(Route::from_hash(""), Route::Home);
+        assert_eq!(Route::from_hash("#/"), Route::Home);
+        assert_eq!(Route::from_hash("#/room/"), Route::Home);
+        assert_eq!(Route::from_hash("#/room/<script>"), Route::Home);
+        assert_eq!(Route::from_hash(&format!("#/room/{}", "a".repeat(65))), Route::Home);
+    }
+
+    #[test]
+    fn goes_back_and_forth_between_routes_and_hashes() {
+        for route in &[Route::Home, Route::Room(String::from("0a1b2c"))] {
+            assert_eq!(&Route::from_hash(&route.to_hash()), route);
+        }
+    }
+}
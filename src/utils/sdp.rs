@@ -0,0 +1,563 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use crate::utils::ice_candidate::IceCandidate;
+use crate::utils::error::CustomError;
+
+// https://tools.ietf.org/html/rfc4566#section-5
+// Only the lines WebRTC relies on are typed, every other line is kept as is
+// so that a parsed description serializes back to the same text.
+
+// Session level lines allowed before and after the timing line, in order
+const FIELDS_BEFORE_TIMING: &str = "iuepcb";
+const FIELDS_AFTER_TIMING: &str = "rzk";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Origin {
+    pub username: String,
+    pub session_id: String,
+    pub session_version: u64,
+    pub network_type: String,
+    pub address_type: String,
+    pub address: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timing {
+    pub start: u64,
+    pub stop: u64,
+}
+
+/// A `<type>=<value>` line that has no dedicated field
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub kind: char,
+    pub value: String,
+}
+
+/// `a=<name>` or `a=<name>:<value>`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fingerprint {
+    pub algorithm: String,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Codec {
+    pub payload_type: u8,
+    pub name: String,
+    pub clock_rate: u32,
+    pub channels: Option<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaDescription {
+    pub kind: String,
+    pub port: u16,
+    pub port_count: Option<u16>,
+    pub protocol: String,
+    pub formats: Vec<String>,
+    pub fields: Vec<Field>,
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionDescription {
+    pub version: u8,
+    pub origin: Origin,
+    pub session_name: String,
+    pub timing: Timing,
+    pub fields: Vec<Field>,
+    pub attributes: Vec<Attribute>,
+    pub media: Vec<MediaDescription>,
+}
+
+fn attribute_value<'a>(attributes: &'a [Attribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.name == name)
+        .and_then(|attribute| attribute.value.as_deref())
+}
+
+impl Attribute {
+    fn parse(value: &str) -> Self {
+        match value.find(':') {
+            Some(index) => Attribute {
+                name: value[..index].to_string(),
+                value: Some(value[index + 1..].to_string()),
+            },
+            None => Attribute {
+                name: value.to_string(),
+                value: None,
+            },
+        }
+    }
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.value {
+            Some(value) => write!(f, "a={}:{}\r\n", self.name, value),
+            None => write!(f, "a={}\r\n", self.name),
+        }
+    }
+}
+
+impl MediaDescription {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        attribute_value(&self.attributes, name)
+    }
+
+    pub fn mid(&self) -> Option<&str> {
+        self.attribute("mid")
+    }
+
+    pub fn is_data_channel(&self) -> bool {
+        self.kind == "application" && self.protocol.contains("SCTP")
+    }
+
+    /// `a=sctp-port` or the legacy `a=sctpmap:<port> webrtc-datachannel <streams>`
+    pub fn sctp_port(&self) -> Option<u16> {
+        self.attribute("sctp-port")
+            .and_then(|port| port.trim().parse().ok())
+            .or_else(|| {
+                self.attribute("sctpmap")
+                    .and_then(|sctpmap| sctpmap.split_whitespace().next())
+                    .and_then(|port| port.parse().ok())
+            })
+    }
+
+    /// Codecs from the `a=rtpmap:<payload type> <name>/<clock rate>[/<channels>]` attributes
+    pub fn codecs(&self) -> Vec<Codec> {
+        self.attributes
+            .iter()
+            .filter(|attribute| attribute.name == "rtpmap")
+            .filter_map(|attribute| {
+                let value = attribute.value.as_deref()?;
+                let mut parts = value.splitn(2, ' ');
+                let payload_type = parts.next()?.parse().ok()?;
+                let mut encoding = parts.next()?.split('/');
+                Some(Codec {
+                    payload_type,
+                    name: encoding.next()?.to_string(),
+                    clock_rate: encoding.next()?.parse().ok()?,
+                    channels: encoding.next().and_then(|channels| channels.parse().ok()),
+                })
+            })
+            .collect()
+    }
+
+    pub fn candidates(&self) -> Vec<Result<IceCandidate, CustomError>> {
+        self.attributes
+            .iter()
+            .filter(|attribute| attribute.name == "candidate")
+            .map(|attribute| {
+                format!("candidate:{}", attribute.value.as_deref().unwrap_or_default())
+                    .parse::<IceCandidate>()
+            })
+            .collect()
+    }
+}
+
+impl SessionDescription {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        attribute_value(&self.attributes, name)
+    }
+
+    // ICE and DTLS attributes can be set at the session or at the media level
+    fn session_or_media_attribute(&self, name: &str) -> Option<&str> {
+        self.attribute(name)
+            .or_else(|| self.media.iter().find_map(|media| media.attribute(name)))
+    }
+
+    pub fn ice_ufrag(&self) -> Option<&str> {
+        self.session_or_media_attribute("ice-ufrag")
+    }
+
+    pub fn ice_pwd(&self) -> Option<&str> {
+        self.session_or_media_attribute("ice-pwd")
+    }
+
+    pub fn fingerprint(&self) -> Option<Fingerprint> {
+        let mut fingerprint = self.session_or_media_attribute("fingerprint")?.splitn(2, ' ');
+        Some(Fingerprint {
+            algorithm: fingerprint.next()?.to_string(),
+            value: fingerprint.next()?.trim().to_string(),
+        })
+    }
+
+    pub fn data_channel(&self) -> Option<&MediaDescription> {
+        self.media.iter().find(|media| media.is_data_channel())
+    }
+
+    /// Checks what a browser needs to negotiate a connection, on top of the syntax
+    pub fn validate_webrtc(&self) -> Result<(), CustomError> {
+        let missing = |what: &str| {
+            Err(CustomError::SdpValidationError(format!("Missing {}", what)))
+        };
+        if self.media.is_empty() {
+            return missing("media section");
+        }
+        for media in &self.media {
+            let attribute = |name: &str| media.attribute(name).or_else(|| self.attribute(name));
+            if attribute("ice-ufrag").is_none() {
+                return missing(format!("ice-ufrag for {} media", media.kind).as_str());
+            }
+            if attribute("ice-pwd").is_none() {
+                return missing(format!("ice-pwd for {} media", media.kind).as_str());
+            }
+            if attribute("fingerprint").is_none() {
+                return missing(format!("DTLS fingerprint for {} media", media.kind).as_str());
+            }
+            if media.is_data_channel() && media.sctp_port().is_none() {
+                return missing("SCTP port for the data channel");
+            }
+        }
+        Ok(())
+    }
+
+    /// One line description of the negotiated parameters, for the logs
+    pub fn summary(&self) -> String {
+        let media = self
+            .media
+            .iter()
+            .map(|media| {
+                if media.is_data_channel() {
+                    format!("data channel (sctp port {})", media.sctp_port().unwrap_or_default())
+                } else {
+                    let codecs = media
+                        .codecs()
+                        .iter()
+                        .map(|codec| codec.name.clone())
+                        .collect::<Vec<String>>()
+                        .join(", ");
+                    format!("{} [{}]", media.kind, codecs)
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!(
+            "ice-ufrag {}, fingerprint {}, media: {}",
+            self.ice_ufrag().unwrap_or("-"),
+            self.fingerprint()
+                .map_or(String::from("-"), |fingerprint| fingerprint.algorithm),
+            media
+        )
+    }
+}
+
+fn parse_error(line: usize, reason: &str) -> CustomError {
+    CustomError::SdpParseError {
+        line,
+        reason: reason.to_string(),
+    }
+}
+
+fn parse_origin(line: usize, value: &str) -> Result<Origin, CustomError> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 6 {
+        return Err(parse_error(line, "o= expects 6 fields"));
+    }
+    Ok(Origin {
+        username: parts[0].to_string(),
+        session_id: parts[1].to_string(),
+        session_version: parts[2]
+            .parse()
+            .map_err(|_| parse_error(line, "Invalid session version"))?,
+        network_type: parts[3].to_string(),
+        address_type: parts[4].to_string(),
+        address: parts[5].to_string(),
+    })
+}
+
+fn parse_timing(line: usize, value: &str) -> Result<Timing, CustomError> {
+    let mut parts = value.split_whitespace();
+    let mut time = || {
+        parts
+            .next()
+            .and_then(|time| time.parse().ok())
+            .ok_or_else(|| parse_error(line, "t= expects a start and a stop time"))
+    };
+    Ok(Timing {
+        start: time()?,
+        stop: time()?,
+    })
+}
+
+fn parse_media(line: usize, value: &str) -> Result<MediaDescription, CustomError> {
+    let mut parts = value.split_whitespace();
+    let kind = parts
+        .next()
+        .ok_or_else(|| parse_error(line, "Missing media type"))?;
+    let mut ports = parts
+        .next()
+        .ok_or_else(|| parse_error(line, "Missing media port"))?
+        .splitn(2, '/');
+    let port = ports
+        .next()
+        .and_then(|port| port.parse().ok())
+        .ok_or_else(|| parse_error(line, "Invalid media port"))?;
+    let port_count = match ports.next() {
+        Some(count) => Some(
+            count
+                .parse()
+                .map_err(|_| parse_error(line, "Invalid media port count"))?,
+        ),
+        None => None,
+    };
+    let protocol = parts
+        .next()
+        .ok_or_else(|| parse_error(line, "Missing media protocol"))?;
+    let formats: Vec<String> = parts.map(String::from).collect();
+    if formats.is_empty() {
+        return Err(parse_error(line, "Missing media format"));
+    }
+
+    Ok(MediaDescription {
+        kind: kind.to_string(),
+        port,
+        port_count,
+        protocol: protocol.to_string(),
+        formats,
+        fields: vec![],
+        attributes: vec![],
+    })
+}
+
+impl FromStr for SessionDescription {
+    type Err = CustomError;
+
+    fn from_str(sdp: &str) -> Result<Self, Self::Err> {
+        let mut version = None;
+        let mut origin = None;
+        let mut session_name = None;
+        let mut timing = None;
+        let mut fields = vec![];
+        let mut attributes = vec![];
+        let mut media: Vec<MediaDescription> = vec![];
+
+        for (index, raw_line) in sdp.lines().enumerate() {
+            let line = index + 1;
+            let raw_line = raw_line.trim_end_matches('\r');
+            if raw_line.is_empty() {
+                continue;
+            }
+            let mut chars = raw_line.chars();
+            let kind = match (chars.next(), chars.next()) {
+                (Some(kind), Some('=')) if kind.is_ascii_lowercase() => kind,
+                _ => return Err(parse_error(line, "Expected <type>=<value>")),
+            };
+            let value = &raw_line[2..];
+
+            if line == 1 && kind != 'v' {
+                return Err(parse_error(line, "The description must start with v="));
+            }
+            if kind == 'm' {
+                media.push(parse_media(line, value)?);
+                continue;
+            }
+
+            match (kind, media.last_mut()) {
+                ('v', None) => {
+                    version = Some(value.parse().map_err(|_| parse_error(line, "Invalid version"))?)
+                }
+                ('o', None) => origin = Some(parse_origin(line, value)?),
+                ('s', None) => session_name = Some(value.to_string()),
+                ('t', None) => timing = Some(parse_timing(line, value)?),
+                ('a', None) => attributes.push(Attribute::parse(value)),
+                ('a', Some(current_media)) => current_media.attributes.push(Attribute::parse(value)),
+                ('v', Some(_)) | ('o', Some(_)) | ('s', Some(_)) | ('t', Some(_)) => {
+                    return Err(parse_error(line, "Session line inside a media section"));
+                }
+                (kind, None) if FIELDS_BEFORE_TIMING.contains(kind) || FIELDS_AFTER_TIMING.contains(kind) => {
+                    fields.push(Field { kind, value: value.to_string() })
+                }
+                (kind, Some(current_media)) if "icbk".contains(kind) => {
+                    current_media.fields.push(Field { kind, value: value.to_string() })
+                }
+                (kind, _) => return Err(parse_error(line, &format!("Unexpected {}= line", kind))),
+            }
+        }
+
+        Ok(SessionDescription {
+            version: version.ok_or_else(|| parse_error(0, "Missing v="))?,
+            origin: origin.ok_or_else(|| parse_error(0, "Missing o="))?,
+            session_name: session_name.ok_or_else(|| parse_error(0, "Missing s="))?,
+            timing: timing.ok_or_else(|| parse_error(0, "Missing t="))?,
+            fields,
+            attributes,
+            media,
+        })
+    }
+}
+
+impl Display for SessionDescription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let origin = &self.origin;
+        write!(f, "v={}\r\n", self.version)?;
+        write!(
+            f,
+            "o={} {} {} {} {} {}\r\n",
+            origin.username,
+            origin.session_id,
+            origin.session_version,
+            origin.network_type,
+            origin.address_type,
+            origin.address
+        )?;
+        write!(f, "s={}\r\n", self.session_name)?;
+        for field in self.fields.iter().filter(|field| FIELDS_BEFORE_TIMING.contains(field.kind)) {
+            write!(f, "{}={}\r\n", field.kind, field.value)?;
+        }
+        write!(f, "t={} {}\r\n", self.timing.start, self.timing.stop)?;
+        for field in self.fields.iter().filter(|field| FIELDS_AFTER_TIMING.contains(field.kind)) {
+            write!(f, "{}={}\r\n", field.kind, field.value)?;
+        }
+        for attribute in &self.attributes {
+            write!(f, "{}", attribute)?;
+        }
+
+        for media in &self.media {
+            write!(f, "m={} {}", media.kind, media.port)?;
+            if let Some(port_count) = media.port_count {
+                write!(f, "/{}", port_count)?;
+            }
+            write!(f, " {} {}\r\n", media.protocol, media.formats.join(" "))?;
+            for field in &media.fields {
+                write!(f, "{}={}\r\n", field.kind, field.value)?;
+            }
+            for attribute in &media.attributes {
+                write!(f, "{}", attribute)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA_CHANNEL_OFFER: &str = "v=0\r\n\
+o=- 4215775240449105457 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=group:BUNDLE 0\r\n\
+a=msid-semantic: WMS\r\n\
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=candidate:842163049 1 udp 1677729535 203.0.113.7 56143 typ srflx raddr 0.0.0.0 rport 0 generation 0\r\n\
+a=ice-ufrag:sXS6\r\n\
+a=ice-pwd:hf4wbYbHy6MhOWzb7QoTVjCy\r\n\
+a=ice-options:trickle\r\n\
+a=fingerprint:sha-256 4E:2D:8E:1A:3A:5B:9C:11:4E:2D:8E:1A:3A:5B:9C:11:4E:2D:8E:1A:3A:5B:9C:11:4E:2D:8E:1A:3A:5B:9C:11\r\n\
+a=setup:actpass\r\n\
+a=mid:0\r\n\
+a=sctp-port:5000\r\n\
+a=max-message-size:262144\r\n";
+
+    const AUDIO_OFFER: &str = "v=0\r\n\
+o=mozilla...THIS_IS_SDPARTA-80.0 1234 0 IN IP4 0.0.0.0\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=fingerprint:sha-256 AA:BB\r\n\
+a=ice-ufrag:abcd\r\n\
+a=ice-pwd:0123456789\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 109 0\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:0\r\n\
+a=rtpmap:109 opus/48000/2\r\n\
+a=rtpmap:0 PCMU/8000\r\n\
+a=sendrecv\r\n";
+
+    #[test]
+    fn parses_a_data_channel_offer() {
+        let description: SessionDescription = DATA_CHANNEL_OFFER.parse().unwrap();
+
+        assert_eq!(description.origin.session_version, 2);
+        assert_eq!(description.attribute("group"), Some("BUNDLE 0"));
+        assert_eq!(description.ice_ufrag(), Some("sXS6"));
+        assert_eq!(description.ice_pwd(), Some("hf4wbYbHy6MhOWzb7QoTVjCy"));
+        assert_eq!(description.fingerprint().unwrap().algorithm, "sha-256");
+
+        let data_channel = description.data_channel().unwrap();
+        assert_eq!(data_channel.mid(), Some("0"));
+        assert_eq!(data_channel.sctp_port(), Some(5000));
+        assert_eq!(data_channel.candidates().len(), 1);
+        assert_eq!(data_channel.candidates()[0].as_ref().unwrap().port, 56143);
+        assert!(description.validate_webrtc().is_ok());
+    }
+
+    #[test]
+    fn reads_codecs_and_session_level_ice_attributes() {
+        let description: SessionDescription = AUDIO_OFFER.parse().unwrap();
+        let codecs = description.media[0].codecs();
+
+        assert_eq!(description.ice_ufrag(), Some("abcd"));
+        assert_eq!(
+            codecs[0],
+            Codec {
+                payload_type: 109,
+                name: String::from("opus"),
+                clock_rate: 48000,
+                channels: Some(2),
+            }
+        );
+        assert_eq!(codecs[1].name, "PCMU");
+        assert_eq!(codecs[1].channels, None);
+        assert!(description.validate_webrtc().is_ok());
+    }
+
+    #[test]
+    fn serializes_back_to_the_same_text() {
+        for sdp in &[DATA_CHANNEL_OFFER, AUDIO_OFFER] {
+            let description: SessionDescription = sdp.parse().unwrap();
+            assert_eq!(&description.to_string(), sdp);
+        }
+    }
+
+    #[test]
+    fn accepts_unix_line_endings() {
+        let description: SessionDescription = DATA_CHANNEL_OFFER.replace("\r\n", "\n").parse().unwrap();
+        assert_eq!(description.to_string(), DATA_CHANNEL_OFFER);
+    }
+
+    #[test]
+    fn reports_the_malformed_line() {
+        let sdp = DATA_CHANNEL_OFFER.replace("t=0 0", "t=0");
+        match sdp.parse::<SessionDescription>() {
+            Err(CustomError::SdpParseError { line, .. }) => assert_eq!(line, 4),
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let sdp = DATA_CHANNEL_OFFER.replace("m=application 9", "m=application nine");
+        match sdp.parse::<SessionDescription>() {
+            Err(CustomError::SdpParseError { line, .. }) => assert_eq!(line, 7),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_descriptions_without_the_version_line() {
+        assert!("o=- 1 1 IN IP4 0.0.0.0\r\n".parse::<SessionDescription>().is_err());
+        assert!("".parse::<SessionDescription>().is_err());
+        assert!("v=0\r\nthis is not sdp\r\n".parse::<SessionDescription>().is_err());
+    }
+
+    #[test]
+    fn rejects_descriptions_webrtc_cannot_use() {
+        let sdp = DATA_CHANNEL_OFFER.replace("a=fingerprint", "a=not-a-fingerprint");
+        let description: SessionDescription = sdp.parse().unwrap();
+        assert!(description.validate_webrtc().is_err());
+
+        let sdp = DATA_CHANNEL_OFFER.replace("a=sctp-port:5000\r\n", "");
+        let description: SessionDescription = sdp.parse().unwrap();
+        assert!(description.validate_webrtc().is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
+
+use crate::utils::error::CustomError;
+use crate::utils::socket::SDPMessage;
+
+// A description is a few kilobytes at most, anything bigger is not one of our codes
+const MAX_DESCRIPTION_SIZE: usize = 64 * 1024;
+const COMPRESSION_LEVEL: u8 = 9;
+
+/// Encodes a complete session description into a string users can copy and paste:
+/// the JSON `SDPMessage`, deflated, in URL safe base64.
+pub fn encode(sdp_message: &SDPMessage) -> Result<String, CustomError> {
+    let json = serde_json::to_vec(sdp_message)?;
+    let compressed = compress_to_vec(&json, COMPRESSION_LEVEL);
+    Ok(base64::encode_config(&compressed, base64::URL_SAFE_NO_PAD))
+}
+
+pub fn decode(session_code: &str) -> Result<SDPMessage, CustomError> {
+    // Chat applications and mail clients tend to wrap long strings
+    let session_code: String = session_code.split_whitespace().collect();
+    let compressed = base64::decode_config(&session_code, base64::URL_SAFE_NO_PAD)
+        .map_err(|err| CustomError::SessionCodeError(format!("it is damaged ({})", err)))?;
+    let json = decompress_to_vec_with_limit(&compressed, MAX_DESCRIPTION_SIZE)
+        .map_err(|err| CustomError::SessionCodeError(format!("it is damaged ({:?})", err)))?;
+    let sdp_message = serde_json::from_slice::<SDPMessage>(&json)
+        .map_err(|err| CustomError::SessionCodeError(format!("it is not a session code ({})", err)))?;
+    sdp_message
+        .parse()
+        .map_err(|err| CustomError::SessionCodeError(format!("it does not describe a connection ({})", err)))?;
+    Ok(sdp_message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::socket::SdpType;
+
+    const OFFER: &str = "v=0\r\n\
+o=- 4215775240449105457 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=ice-ufrag:sXS6\r\n\
+a=ice-pwd:hf4wbYbHy6MhOWzb7QoTVjCy\r\n\
+a=fingerprint:sha-256 4E:2D:8E:1A:3A:5B:9C:11\r\n\
+a=sctp-port:5000\r\n";
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let sdp_message = SDPMessage {
+            type_: SdpType::Offer,
+            sdp: String::from(OFFER),
+        };
+        let session_code = encode(&sdp_message).unwrap();
+
+        assert!(session_code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(decode(&session_code).unwrap(), sdp_message);
+
+        let wrapped = format!("  {}\n{}  ", &session_code[..10], &session_code[10..]);
+        assert_eq!(decode(&wrapped).unwrap(), sdp_message);
+    }
+
+    #[test]
+    fn rejects_invalid_codes() {
+        assert!(decode("").is_err());
+        assert!(decode("not a session code!").is_err());
+        assert!(decode(&base64::encode_config(b"plain text", base64::URL_SAFE_NO_PAD)).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::utils::error::CustomError;
+use crate::utils::storage;
+use crate::utils::theme::{CustomTheme, Theme};
+
+const SETTINGS_KEY: &str = "prust-settings";
+const MAX_DISPLAY_NAME_LENGTH: usize = 64;
+const DEFAULT_STUN_SERVER: &str = "stun:stun.l.google.com:19302";
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// `MIGRATIONS[n]` turns the settings of version `n + 1` into version `n + 2`.
+/// New fields only need a default, the migrations are for the renamed or reinterpreted ones.
+const MIGRATIONS: &[Migration] = &[];
+
+pub const SETTINGS_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SendKey {
+    /// Shift+Enter goes to the next line
+    Enter,
+    /// Enter goes to the next line
+    CtrlEnter,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    /// Errors are always shown, the user may have to act on them
+    pub info: bool,
+    pub warnings: bool,
+    pub unread_badges: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            info: true,
+            warnings: true,
+            unread_badges: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IceServer {
+    pub urls: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+}
+
+impl IceServer {
+    /// Reads a line of the settings form: `<url> [<username> <credential>]`
+    pub fn parse_line(line: &str) -> Result<Self, CustomError> {
+        let mut parts = line.split_whitespace();
+        let urls = parts.next().unwrap_or_default().to_string();
+        let server = match (parts.next(), parts.next(), parts.next()) {
+            (None, _, _) => IceServer {
+                urls,
+                username: None,
+                credential: None,
+            },
+            (Some(username), Some(credential), None) => IceServer {
+                urls,
+                username: Some(username.to_string()),
+                credential: Some(credential.to_string()),
+            },
+            _ => {
+                return Err(CustomError::SettingsError(format!(
+                    "\"{}\" is not a URL followed by a username and a password",
+                    line.trim()
+                )))
+            }
+        };
+        server.validate()?;
+        Ok(server)
+    }
+
+    pub fn to_line(&self) -> String {
+        match (&self.username, &self.credential) {
+            (Some(username), Some(credential)) => format!("{} {} {}", self.urls, username, credential),
+            _ => self.urls.clone(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), CustomError> {
+        let scheme = self.urls.split(':').next().unwrap_or_default();
+        match scheme {
+            "stun" | "stuns" => Ok(()),
+            "turn" | "turns" if self.username.is_some() && self.credential.is_some() => Ok(()),
+            "turn" | "turns" => Err(CustomError::SettingsError(format!(
+                "the TURN server {} needs a username and a password",
+                self.urls
+            ))),
+            _ => Err(CustomError::SettingsError(format!(
+                "{} is not a stun: or turn: URL",
+                self.urls
+            ))),
+        }
+    }
+}
+
+/// Preferences of the user, kept in localStorage
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    /// Filled in the connection forms
+    pub display_name: String,
+    pub theme: Theme,
+    /// Imported by the user, shown with `Theme::Custom`
+    pub custom_theme: Option<CustomTheme>,
+    pub notifications: NotificationSettings,
+    pub send_key: SendKey,
+    /// Used by the next connections
+    pub ice_servers: Vec<IceServer>,
+    /// Messages kept by conversation, the oldest go first. `None` keeps them all.
+    pub history_limit: Option<usize>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            display_name: String::new(),
+            theme: Theme::System,
+            custom_theme: None,
+            notifications: NotificationSettings::default(),
+            send_key: SendKey::Enter,
+            ice_servers: vec![IceServer {
+                urls: String::from(DEFAULT_STUN_SERVER),
+                username: None,
+                credential: None,
+            }],
+            history_limit: None,
+        }
+    }
+}
+
+// The first settings may not have a version
+fn version_of(settings: &Map<String, Value>) -> usize {
+    settings.get("version").and_then(Value::as_u64).unwrap_or(1).max(1) as usize
+}
+
+fn newer_version_error(version: usize) -> CustomError {
+    CustomError::SettingsError(format!(
+        "version {} comes from a newer version of the application, reset them to replace them",
+        version
+    ))
+}
+
+fn migrate(mut settings: Map<String, Value>, migrations: &[Migration]) -> Result<Map<String, Value>, CustomError> {
+    let latest = migrations.len() + 1;
+    let version = version_of(&settings);
+    if version > latest {
+        return Err(newer_version_error(version));
+    }
+    for migration in &migrations[version - 1..] {
+        migration(&mut settings);
+    }
+    settings.insert(String::from("version"), Value::from(latest));
+    Ok(settings)
+}
+
+fn from_value(value: Value, migrations: &[Migration]) -> Result<Settings, CustomError> {
+    match value {
+        Value::Object(settings) => Ok(serde_json::from_value(Value::Object(migrate(settings, migrations)?))?),
+        _ => Err(CustomError::SettingsError(String::from("the settings are not an object"))),
+    }
+}
+
+// Unreadable settings can be replaced, those of a newer version of the application only by a reset
+fn check_replaceable(stored: Option<Value>, latest: usize) -> Result<(), CustomError> {
+    match stored {
+        Some(Value::Object(settings)) if version_of(&settings) > latest => Err(newer_version_error(version_of(&settings))),
+        _ => Ok(()),
+    }
+}
+
+impl Settings {
+    /// Read again by each reader, the user may have changed them since.
+    /// The default ones when they cannot be read.
+    pub fn load() -> Self {
+        Settings::try_load().unwrap_or_else(|err| {
+            log::error!("Could not read the settings, using the default ones: {}", err);
+            Settings::default()
+        })
+    }
+
+    pub fn try_load() -> Result<Self, CustomError> {
+        match storage::load::<Value>(SETTINGS_KEY)? {
+            Some(value) => from_value(value, MIGRATIONS),
+            None => Ok(Settings::default()),
+        }
+    }
+
+    /// Refuses to overwrite the settings of a newer version of the application
+    pub fn save(&self) -> Result<(), CustomError> {
+        check_replaceable(storage::load::<Value>(SETTINGS_KEY).ok().flatten(), MIGRATIONS.len() + 1)?;
+        storage::save(SETTINGS_KEY, self)
+    }
+
+    /// Replaces the stored settings, whatever their version
+    pub fn reset() -> Result<Self, CustomError> {
+        let settings = Settings::default();
+        storage::save(SETTINGS_KEY, &settings)?;
+        Ok(settings)
+    }
+
+    pub fn validate(&self) -> Result<(), CustomError> {
+        if self.display_name.chars().count() > MAX_DISPLAY_NAME_LENGTH {
+            return Err(CustomError::SettingsError(format!(
+                "the name is longer than {} characters",
+                MAX_DISPLAY_NAME_LENGTH
+            )));
+        }
+        match (&self.custom_theme, self.theme) {
+            (Some(custom_theme), _) => custom_theme.validate()?,
+            (None, Theme::Custom) => {
+                return Err(CustomError::SettingsError(String::from("the custom theme has not been imported")))
+            }
+            _ => (),
+        }
+        if self.history_limit == Some(0) {
+            return Err(CustomError::SettingsError(String::from("at least one message must be kept")));
+        }
+        self.ice_servers.iter().try_for_each(IceServer::validate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename_name(settings: &mut Map<String, Value>) {
+        if let Some(name) = settings.remove("name") {
+            settings.insert(String::from("display_name"), name);
+        }
+    }
+
+    fn drop_limit(settings: &mut Map<String, Value>) {
+        settings.remove("history_limit");
+    }
+
+    #[test]
+    fn fills_the_missing_fields() {
+        let settings = from_value(serde_json::json!({ "send_key": "ctrl-enter" }), MIGRATIONS).unwrap();
+        assert_eq!(settings.send_key, SendKey::CtrlEnter);
+        assert_eq!(settings.ice_servers, Settings::default().ice_servers);
+        assert_eq!(settings.version, SETTINGS_VERSION);
+    }
+
+    #[test]
+    fn runs_the_migrations_from_the_stored_version() {
+        let migrations: &[Migration] = &[rename_name, drop_limit];
+        let settings = serde_json::json!({ "version": 1, "name": "alice", "history_limit": 10 });
+        let settings = from_value(settings, migrations).unwrap();
+        assert_eq!(settings.display_name, "alice");
+        assert_eq!(settings.history_limit, None);
+        assert_eq!(settings.version, 3);
+
+        // Already renamed, only the last migration runs
+        let settings = serde_json::json!({ "version": 2, "name": "bob", "history_limit": 10 });
+        let settings = from_value(settings, migrations).unwrap();
+        assert_eq!(settings.display_name, "");
+    }
+
+    #[test]
+    fn refuses_the_settings_of_a_newer_version() {
+        let settings = serde_json::json!({ "version": SETTINGS_VERSION + 1 });
+        assert!(from_value(settings, MIGRATIONS).is_err());
+        assert!(from_value(serde_json::json!([]), MIGRATIONS).is_err());
+    }
+
+    #[test]
+    fn only_replaces_the_settings_of_this_version_or_older() {
+        assert!(check_replaceable(None, 2).is_ok());
+        assert!(check_replaceable(Some(serde_json::json!("unreadable")), 2).is_ok());
+        assert!(check_replaceable(Some(serde_json::json!({ "version": 1 })), 2).is_ok());
+        assert!(check_replaceable(Some(serde_json::json!({ "version": 3 })), 2).is_err());
+    }
+
+    #[test]
+    fn reads_the_ice_servers_of_the_form() {
+        let stun = IceServer::parse_line("stun:stun.example.org:3478").unwrap();
+        assert_eq!(stun.username, None);
+        let turn = IceServer::parse_line(" turn:turn.example.org:3478 alice s3cret ").unwrap();
+        assert_eq!(turn.to_line(), "turn:turn.example.org:3478 alice s3cret");
+
+        assert!(IceServer::parse_line("turn:turn.example.org:3478").is_err());
+        assert!(IceServer::parse_line("https://example.org").is_err());
+        assert!(IceServer::parse_line("stun:example.org alice").is_err());
+    }
+}
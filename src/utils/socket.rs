@@ -1,10 +1,12 @@
-use std::{convert::TryFrom, fmt::Debug};
+use std::{convert::TryFrom, fmt::Debug, str::FromStr};
 
-use crate::Participants;
+use crate::utils::error::CustomError;
+use crate::utils::participants::Participants;
+use crate::utils::sdp::SessionDescription;
 use serde::{Deserialize, Serialize};
 use web_sys::{MessageEvent, RtcSdpType, RtcSessionDescription, RtcSessionDescriptionInit};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 pub enum SocketMessage {
     #[serde(rename = "newUser")]
@@ -15,49 +17,110 @@ pub enum SocketMessage {
     SignalMessageToClient { content: SignalingMessage },
     #[serde(rename = "joined_room")]
     JoinedRoom { content: Room },
+    #[serde(rename = "leave_room")]
+    LeaveRoom { content: Room },
+    /// Announces the user, the server answers with a `PresenceNotify` for each contact online,
+    /// then one each time a contact comes or goes. Sent again, it replaces the contacts.
+    #[serde(rename = "presence_subscribe")]
+    PresenceSubscribe { content: PresenceSubscription },
+    #[serde(rename = "presence_notify")]
+    PresenceNotify { content: Presence },
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Room {
     pub room: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PresenceSubscription {
+    pub user: String,
+    pub contacts: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Presence {
+    pub user: String,
+    pub online: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(tag = "signalType")]
 pub enum SignalingMessage {
     #[serde(rename = "userHere")]
     UserHere { message: u16 },
     #[serde(rename = "ice_candidate")]
     ICECandidate { message: Candidate },
+    /// `order` counts the descriptions of the sender from 1, 0 for the clients which do not
     #[serde(rename = "SDP")]
-    SDP { message: SDPMessage },
+    SDP {
+        message: SDPMessage,
+        #[serde(default)]
+        order: u32,
+    },
+    #[serde(rename = "userLeft")]
+    UserLeft,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SdpType {
+    Offer,
+    Pranswer,
+    Answer,
+    Rollback,
+}
+
+impl From<SdpType> for RtcSdpType {
+    fn from(value: SdpType) -> Self {
+        match value {
+            SdpType::Offer => RtcSdpType::Offer,
+            SdpType::Pranswer => RtcSdpType::Pranswer,
+            SdpType::Answer => RtcSdpType::Answer,
+            SdpType::Rollback => RtcSdpType::Rollback,
+        }
+    }
+}
+
+impl TryFrom<RtcSdpType> for SdpType {
+    type Error = CustomError;
+
+    fn try_from(value: RtcSdpType) -> Result<Self, Self::Error> {
+        match value {
+            RtcSdpType::Offer => Ok(SdpType::Offer),
+            RtcSdpType::Pranswer => Ok(SdpType::Pranswer),
+            RtcSdpType::Answer => Ok(SdpType::Answer),
+            RtcSdpType::Rollback => Ok(SdpType::Rollback),
+            _ => Err(CustomError::InputTypeError(String::from("Unknown SDP type"))),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct SDPMessage {
     #[serde(rename = "type")]
-    pub type_: String,
+    pub type_: SdpType,
     pub sdp: String,
 }
 
+impl SDPMessage {
+    /// Parses the description, a rollback has no description to parse
+    pub fn parse(&self) -> Result<Option<SessionDescription>, CustomError> {
+        if self.type_ == SdpType::Rollback {
+            return Ok(None);
+        }
+        let description = self.sdp.parse::<SessionDescription>()?;
+        description.validate_webrtc()?;
+        Ok(Some(description))
+    }
+}
+
 impl TryFrom<RtcSessionDescription> for SDPMessage {
     type Error = CustomError;
 
     fn try_from(value: RtcSessionDescription) -> Result<Self, Self::Error> {
-        // TODO: This mapping looks fishy, improve this.
-        let type_ = match value.type_() {
-            RtcSdpType::Answer => "answer",
-            RtcSdpType::Offer => "offer",
-            RtcSdpType::Pranswer => "pranswer",
-            RtcSdpType::Rollback => "rollback",
-            _ => {
-                return Result::Err(CustomError::InputTypeError(
-                    String::from("Unknown SDP type")
-                ));
-            }
-        };
         Ok(SDPMessage {
-            type_: type_.to_string(),
+            type_: SdpType::try_from(value.type_())?,
             sdp: value.sdp(),
         })
     }
@@ -67,31 +130,30 @@ impl TryFrom<SDPMessage> for RtcSessionDescriptionInit {
     type Error = CustomError;
 
     fn try_from(value: SDPMessage) -> Result<Self, Self::Error> {
-        // TODO: This mapping looks fishy, improve this.
-        let type_ = match value.type_.as_str() {
-            "answer" => RtcSdpType::Answer,
-            "offer" => RtcSdpType::Offer,
-            "pranswer" => RtcSdpType::Pranswer,
-            "rollback" => RtcSdpType::Rollback,
-            _ => {
-                return Result::Err(CustomError::InputTypeError(String::from(
-                    "SDP type not found",
-                )));
-            }
-        };
-        let mut res = RtcSessionDescriptionInit::new(type_);
-        res.sdp(&value.sdp);
+        // Malformed descriptions are rejected here rather than by the browser, with a precise error
+        value.parse()?;
+        let res = RtcSessionDescriptionInit::new(value.type_.into());
+        res.set_sdp(&value.sdp);
         Ok(res)
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Candidate {
     pub candidate: String,
     pub sdp_mid: String,
     pub sdp_m_line_index: u16,
 }
 
+// Separate from the MessageEvent conversion so it can be fuzzed natively, see fuzz/
+impl FromStr for SocketMessage {
+    type Err = CustomError;
+
+    fn from_str(json: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_str::<SocketMessage>(json)?)
+    }
+}
+
 impl TryFrom<MessageEvent> for SocketMessage {
     type Error = CustomError;
 
@@ -99,19 +161,82 @@ impl TryFrom<MessageEvent> for SocketMessage {
         let string_data = message_event.data().as_string().ok_or_else(|| {
             CustomError::InputTypeError(String::from("MessageEvent.data is not a String"))
         })?;
-        Ok(serde_json::from_str::<SocketMessage>(&string_data)?)
+        string_data.parse()
     }
 }
 
-// ERRORS
-#[derive(Debug)]
-pub enum CustomError {
-    InputTypeError(String),
-    JsonParseError(serde_json::Error),
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sdp_message_keeps_the_browser_format() {
+        let json = r#"{"type":"answer","sdp":"v=0\r\n"}"#;
+        let sdp_message = serde_json::from_str::<SDPMessage>(json).unwrap();
+
+        assert_eq!(sdp_message.type_, SdpType::Answer);
+        assert_eq!(serde_json::to_string(&sdp_message).unwrap(), json);
+    }
+
+    #[test]
+    fn sdp_message_rejects_unknown_types() {
+        let json = r#"{"type":"question","sdp":""}"#;
+        assert!(serde_json::from_str::<SDPMessage>(json).is_err());
+    }
+
+    #[test]
+    fn socket_message_rejects_malformed_json() {
+        let json = r#"{"type":"signal_message_to_client","content":{"signalType":"userHere","message":70000}}"#;
+        assert!(json.parse::<SocketMessage>().is_err());
+        assert!("{".parse::<SocketMessage>().is_err());
 
-impl From<serde_json::Error> for CustomError {
-    fn from(error: serde_json::Error) -> Self {
-        CustomError::JsonParseError(error)
+        let json = r#"{"type":"signal_message_to_client","content":{"signalType":"userHere","message":1}}"#;
+        match json.parse::<SocketMessage>().unwrap() {
+            SocketMessage::SignalMessageToClient {
+                content: SignalingMessage::UserHere { message },
+            } => assert_eq!(message, 1),
+            other => panic!("Unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn presence_messages_keep_the_server_format() {
+        let json = r#"{"type":"presence_notify","content":{"user":"bob","online":true}}"#;
+        match json.parse::<SocketMessage>().unwrap() {
+            SocketMessage::PresenceNotify { content } => assert_eq!(
+                content,
+                Presence {
+                    user: String::from("bob"),
+                    online: true
+                }
+            ),
+            other => panic!("Unexpected message {:?}", other),
+        }
+
+        let subscribe = SocketMessage::PresenceSubscribe {
+            content: PresenceSubscription {
+                user: String::from("alice"),
+                contacts: vec![String::from("bob")],
+            },
+        };
+        assert_eq!(
+            serde_json::to_string(&subscribe).unwrap(),
+            r#"{"type":"presence_subscribe","content":{"user":"alice","contacts":["bob"]}}"#
+        );
+    }
+
+    #[test]
+    fn rollback_has_no_description() {
+        let sdp_message = SDPMessage {
+            type_: SdpType::Rollback,
+            sdp: String::new(),
+        };
+        assert!(sdp_message.parse().unwrap().is_none());
+
+        let sdp_message = SDPMessage {
+            type_: SdpType::Offer,
+            sdp: String::new(),
+        };
+        assert!(sdp_message.parse().is_err());
     }
 }
@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+
+/// Subset of the `RTCStats` dictionaries we care about.
+/// Every stats type shares this struct, fields that do not apply are left empty.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RawStats {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub id: String,
+    // candidate-pair
+    pub state: Option<String>,
+    pub nominated: Option<bool>,
+    // Firefox flags the selected pair instead of exposing it on the transport
+    pub selected: Option<bool>,
+    pub local_candidate_id: Option<String>,
+    pub remote_candidate_id: Option<String>,
+    pub current_round_trip_time: Option<f64>,
+    pub requests_sent: Option<u64>,
+    pub responses_received: Option<u64>,
+    // local-candidate / remote-candidate
+    pub candidate_type: Option<String>,
+    pub address: Option<String>,
+    pub ip: Option<String>,
+    pub port: Option<u16>,
+    pub protocol: Option<String>,
+    // data-channel
+    pub label: Option<String>,
+    pub bytes_sent: Option<u64>,
+    pub bytes_received: Option<u64>,
+    // transport
+    pub selected_candidate_pair_id: Option<String>,
+    // inbound-rtp, only present once media is exchanged
+    pub packets_lost: Option<i64>,
+    pub packets_received: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CandidateStats {
+    pub candidate_type: String,
+    pub address: String,
+    pub port: u16,
+    pub protocol: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CandidatePairStats {
+    pub local: Option<CandidateStats>,
+    pub remote: Option<CandidateStats>,
+    pub round_trip_time_ms: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DataChannelStats {
+    pub label: String,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// Snapshot of the connection built from a `getStats()` report.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ConnectionStats {
+    pub timestamp: f64,
+    pub selected_pair: Option<CandidatePairStats>,
+    pub data_channels: Vec<DataChannelStats>,
+    /// Ratio between 0 and 1, of the media streams
+    pub packet_loss: Option<f64>,
+    /// Ratio between 0 and 1 of the STUN consent checks of the selected pair left unanswered,
+    /// some are in flight at any time: it is not a packet loss
+    pub check_loss: Option<f64>,
+}
+
+impl ConnectionStats {
+    pub fn from_raw_stats(timestamp: f64, raw_stats: &[RawStats]) -> Self {
+        let find_by_id = |id: &str| raw_stats.iter().find(|stats| stats.id == id);
+
+        let selected_pair_id = raw_stats
+            .iter()
+            .filter(|stats| stats.type_ == "transport")
+            .find_map(|stats| stats.selected_candidate_pair_id.clone());
+        let selected_pair = match selected_pair_id {
+            Some(id) => find_by_id(&id),
+            None => raw_stats.iter().filter(|stats| stats.type_ == "candidate-pair").find(|stats| {
+                stats.selected == Some(true)
+                    || (stats.nominated == Some(true) && stats.state.as_deref() == Some("succeeded"))
+            }),
+        };
+
+        let candidate = |id: &Option<String>| {
+            id.as_deref().and_then(find_by_id).map(|stats| CandidateStats {
+                candidate_type: stats.candidate_type.clone().unwrap_or_default(),
+                address: stats
+                    .address
+                    .clone()
+                    .or_else(|| stats.ip.clone())
+                    .unwrap_or_default(),
+                port: stats.port.unwrap_or_default(),
+                protocol: stats.protocol.clone().unwrap_or_default(),
+            })
+        };
+
+        let data_channels = raw_stats
+            .iter()
+            .filter(|stats| stats.type_ == "data-channel")
+            .map(|stats| DataChannelStats {
+                label: stats.label.clone().unwrap_or_default(),
+                bytes_sent: stats.bytes_sent.unwrap_or_default(),
+                bytes_received: stats.bytes_received.unwrap_or_default(),
+            })
+            .collect();
+
+        Self {
+            timestamp,
+            selected_pair: selected_pair.map(|pair| CandidatePairStats {
+                local: candidate(&pair.local_candidate_id),
+                remote: candidate(&pair.remote_candidate_id),
+                round_trip_time_ms: pair.current_round_trip_time.map(|rtt| rtt * 1000.0),
+            }),
+            data_channels,
+            packet_loss: ConnectionStats::packet_loss(raw_stats),
+            check_loss: selected_pair.and_then(ConnectionStats::check_loss),
+        }
+    }
+
+    // Only the media streams report their losses, not the data channels
+    fn packet_loss(raw_stats: &[RawStats]) -> Option<f64> {
+        let (lost, received) = raw_stats
+            .iter()
+            .filter(|stats| stats.type_ == "inbound-rtp")
+            .fold((0, 0), |(lost, received), stats| {
+                (
+                    lost + stats.packets_lost.unwrap_or_default().max(0) as u64,
+                    received + stats.packets_received.unwrap_or_default(),
+                )
+            });
+        if lost + received > 0 {
+            Some(lost as f64 / (lost + received) as f64)
+        } else {
+            None
+        }
+    }
+
+    fn check_loss(pair: &RawStats) -> Option<f64> {
+        match (pair.requests_sent, pair.responses_received) {
+            (Some(sent), Some(received)) if sent > 0 => {
+                Some(sent.saturating_sub(received) as f64 / sent as f64)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(type_: &str, id: &str) -> RawStats {
+        RawStats {
+            type_: type_.to_string(),
+            id: id.to_string(),
+            ..RawStats::default()
+        }
+    }
+
+    fn candidate(id: &str, candidate_type: &str, ip: &str) -> RawStats {
+        RawStats {
+            candidate_type: Some(candidate_type.to_string()),
+            ip: Some(ip.to_string()),
+            port: Some(3478),
+            protocol: Some(String::from("udp")),
+            ..stats("local-candidate", id)
+        }
+    }
+
+    fn pair(id: &str, requests_sent: u64, responses_received: u64) -> RawStats {
+        RawStats {
+            local_candidate_id: Some(String::from("local")),
+            remote_candidate_id: Some(String::from("remote")),
+            current_round_trip_time: Some(0.025),
+            requests_sent: Some(requests_sent),
+            responses_received: Some(responses_received),
+            ..stats("candidate-pair", id)
+        }
+    }
+
+    fn transport(selected_pair: &str) -> RawStats {
+        RawStats {
+            selected_candidate_pair_id: Some(selected_pair.to_string()),
+            ..stats("transport", "transport")
+        }
+    }
+
+    fn inbound(packets_lost: i64, packets_received: u64) -> RawStats {
+        RawStats {
+            packets_lost: Some(packets_lost),
+            packets_received: Some(packets_received),
+            ..stats("inbound-rtp", "inbound")
+        }
+    }
+
+    #[test]
+    fn reads_the_selected_pair_of_the_transport() {
+        let raw_stats = vec![
+            transport("pair"),
+            pair("other", 10, 10),
+            pair("pair", 20, 19),
+            candidate("local", "host", "192.168.1.20"),
+            candidate("remote", "srflx", "203.0.113.7"),
+        ];
+        let connection_stats = ConnectionStats::from_raw_stats(1.0, &raw_stats);
+        let selected_pair = connection_stats.selected_pair.unwrap();
+
+        assert_eq!(selected_pair.local.unwrap().address, "192.168.1.20");
+        assert_eq!(selected_pair.remote.unwrap().candidate_type, "srflx");
+        assert_eq!(selected_pair.round_trip_time_ms, Some(25.0));
+        assert_eq!(connection_stats.check_loss, Some(0.05));
+    }
+
+    #[test]
+    fn falls_back_on_the_nominated_pair() {
+        let raw_stats = vec![
+            RawStats {
+                nominated: Some(true),
+                state: Some(String::from("in-progress")),
+                ..pair("waiting", 4, 0)
+            },
+            RawStats {
+                nominated: Some(true),
+                state: Some(String::from("succeeded")),
+                ..pair("pair", 4, 3)
+            },
+        ];
+        let connection_stats = ConnectionStats::from_raw_stats(1.0, &raw_stats);
+
+        assert!(connection_stats.selected_pair.is_some());
+        assert_eq!(connection_stats.check_loss, Some(0.25));
+        // A data channel only connection has no packet loss to report
+        assert_eq!(connection_stats.packet_loss, None);
+    }
+
+    #[test]
+    fn reports_the_losses_of_the_media_streams() {
+        let raw_stats = vec![transport("pair"), pair("pair", 10, 10), inbound(5, 95), inbound(-2, 100)];
+        let connection_stats = ConnectionStats::from_raw_stats(1.0, &raw_stats);
+
+        // A negative count comes from duplicated packets, it is not a gain
+        assert_eq!(connection_stats.packet_loss, Some(5.0 / 200.0));
+        assert_eq!(connection_stats.check_loss, Some(0.0));
+    }
+
+    #[test]
+    fn has_nothing_to_report_without_a_selected_pair_or_a_request() {
+        let connection_stats = ConnectionStats::from_raw_stats(1.0, &[pair("pair", 10, 5)]);
+        assert_eq!(connection_stats.selected_pair, None);
+        assert_eq!(connection_stats.check_loss, None);
+        assert_eq!(connection_stats.packet_loss, None);
+
+        let connection_stats = ConnectionStats::from_raw_stats(1.0, &[transport("pair"), pair("pair", 0, 0)]);
+        assert!(connection_stats.selected_pair.is_some());
+        assert_eq!(connection_stats.check_loss, None);
+    }
+}
@@ -0,0 +1,32 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use web_sys::Storage;
+
+use crate::utils::error::{js_error_message, CustomError};
+
+// localStorage throws in private modes and can be disabled, every access may fail
+fn local_storage() -> Result<Storage, CustomError> {
+    web_sys::window()
+        .ok_or_else(|| CustomError::StorageError(String::from("No window")))?
+        .local_storage()
+        .map_err(|err| CustomError::StorageError(js_error_message(&err)))?
+        .ok_or_else(|| CustomError::StorageError(String::from("localStorage is disabled")))
+}
+
+/// Reads the JSON value stored under `key`, `None` when there is none
+pub fn load<T: DeserializeOwned>(key: &str) -> Result<Option<T>, CustomError> {
+    let json = local_storage()?
+        .get_item(key)
+        .map_err(|err| CustomError::StorageError(js_error_message(&err)))?;
+    match json {
+        Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn save<T: Serialize>(key: &str, value: &T) -> Result<(), CustomError> {
+    let json = serde_json::to_string(value)?;
+    local_storage()?
+        .set_item(key, &json)
+        .map_err(|err| CustomError::StorageError(js_error_message(&err)))
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsCast;
+use web_sys::HtmlElement;
+
+use crate::utils::error::CustomError;
+
+const MAX_VALUE_LENGTH: usize = 64;
+
+/// The custom properties of `static/variables.css` a palette defines, without their `--`
+pub const THEME_VARIABLES: &[&str] = &[
+    "color-text",
+    "color-muted",
+    "color-accent",
+    "color-accent-soft",
+    "color-on-accent",
+    "color-border",
+    "background-panel",
+    "background-connect",
+    "background-message-me",
+    "background-message-u",
+    "status-connected",
+    "status-pending",
+    "status-error",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// Light or dark with `prefers-color-scheme`
+    System,
+    Light,
+    Dark,
+    HighContrast,
+    /// The `CustomTheme` of the settings
+    Custom,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 5] = [Theme::System, Theme::Light, Theme::Dark, Theme::HighContrast, Theme::Custom];
+
+    /// Also the `data-theme` of the root element, matched by the style sheets
+    pub fn value(&self) -> &'static str {
+        match self {
+            Theme::System => "system",
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::HighContrast => "high-contrast",
+            Theme::Custom => "custom",
+        }
+    }
+
+    pub fn from_value(value: &str) -> Option<Self> {
+        Theme::ALL.iter().find(|theme| theme.value() == value).copied()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Theme::System => "Same as the system",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+            Theme::HighContrast => "High contrast",
+            Theme::Custom => "Custom",
+        }
+    }
+
+    /// `system_dark` comes from `prefers-color-scheme`
+    pub fn is_dark(&self, custom: Option<&CustomTheme>, system_dark: bool) -> bool {
+        match self {
+            Theme::System => system_dark,
+            Theme::Light => false,
+            Theme::Dark | Theme::HighContrast => true,
+            Theme::Custom => custom.map_or(system_dark, |custom| custom.base.is_dark(None, system_dark)),
+        }
+    }
+
+    /// What the toggle shows next
+    pub fn toggled(&self, custom: Option<&CustomTheme>, system_dark: bool) -> Theme {
+        if self.is_dark(custom, system_dark) {
+            Theme::Light
+        } else {
+            Theme::Dark
+        }
+    }
+}
+
+/// Imported by the user as JSON, its colors override the palette of its base:
+/// `{"name": "Night", "base": "dark", "colors": {"color-accent": "#ff9800"}}`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CustomTheme {
+    pub name: String,
+    #[serde(default = "default_base")]
+    pub base: Theme,
+    pub colors: BTreeMap<String, String>,
+}
+
+fn default_base() -> Theme {
+    Theme::Light
+}
+
+impl CustomTheme {
+    pub fn parse(json: &str) -> Result<Self, CustomError> {
+        let theme: CustomTheme = serde_json::from_str(json)
+            .map_err(|err| CustomError::SettingsError(format!("the custom theme is not valid: {}", err)))?;
+        theme.validate()?;
+        Ok(theme)
+    }
+
+    pub fn validate(&self) -> Result<(), CustomError> {
+        if self.base == Theme::Custom {
+            return Err(CustomError::SettingsError(String::from(
+                "a custom theme cannot be based on another one",
+            )));
+        }
+        for (name, value) in &self.colors {
+            if !THEME_VARIABLES.contains(&name.as_str()) {
+                return Err(CustomError::SettingsError(format!(
+                    "the custom theme sets {}, the colors are {}",
+                    name,
+                    THEME_VARIABLES.join(", ")
+                )));
+            }
+            // A value is written in a style attribute, it must not end the declaration
+            let value = value.trim();
+            if value.is_empty() || value.len() > MAX_VALUE_LENGTH || value.contains(|c| ";{}<>\\".contains(c)) {
+                return Err(CustomError::SettingsError(format!(
+                    "\"{}\" is not a color for {}",
+                    value, name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sets `data-theme` on the root element, with the colors of the custom theme on top
+pub fn apply(theme: Theme, custom: Option<&CustomTheme>) {
+    let root = match web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.document_element())
+        .and_then(|root| root.dyn_into::<HtmlElement>().ok())
+    {
+        Some(root) => root,
+        None => return,
+    };
+    let custom = custom.filter(|_| theme == Theme::Custom);
+    let data_theme = custom.map_or(theme, |custom| custom.base).value();
+    if let Err(err) = root.set_attribute("data-theme", data_theme) {
+        log::error!("Could not apply the theme {:?}", err);
+    }
+
+    // The colors of the previous custom theme go first
+    let style = root.style();
+    for name in THEME_VARIABLES {
+        if let Err(err) = style.remove_property(&format!("--{}", name)) {
+            log::error!("Could not reset the color {} {:?}", name, err);
+        }
+    }
+    for (name, value) in custom.iter().flat_map(|custom| custom.colors.iter()) {
+        if let Err(err) = style.set_property(&format!("--{}", name), value.trim()) {
+            log::error!("Could not set the color {} {:?}", name, err);
+        }
+    }
+}
+
+/// Whether the system asks for a dark palette
+pub fn system_prefers_dark() -> bool {
+    web_sys::window()
+        .and_then(|window| window.match_media("(prefers-color-scheme: dark)").ok().flatten())
+        .is_some_and(|query| query.matches())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_a_custom_theme() {
+        let theme = CustomTheme::parse(r##"{"name": "Night", "base": "dark", "colors": {"color-accent": "#ff9800"}}"##).unwrap();
+        assert_eq!(theme.base, Theme::Dark);
+        assert_eq!(theme.colors["color-accent"], "#ff9800");
+
+        let theme = CustomTheme::parse(r#"{"name": "Plain", "colors": {}}"#).unwrap();
+        assert_eq!(theme.base, Theme::Light);
+    }
+
+    #[test]
+    fn refuses_the_unknown_colors_and_the_injections() {
+        assert!(CustomTheme::parse(r#"{"name": "Bad", "colors": {"width-sidebar": "10px"}}"#).is_err());
+        assert!(CustomTheme::parse(r#"{"name": "Bad", "colors": {"color-text": "red; display: none"}}"#).is_err());
+        assert!(CustomTheme::parse(r#"{"name": "Bad", "colors": {"color-text": " "}}"#).is_err());
+        assert!(CustomTheme::parse(r#"{"name": "Bad", "base": "custom", "colors": {}}"#).is_err());
+        assert!(CustomTheme::parse("not json").is_err());
+    }
+
+    #[test]
+    fn toggles_to_the_other_brightness() {
+        assert_eq!(Theme::Light.toggled(None, true), Theme::Dark);
+        assert_eq!(Theme::HighContrast.toggled(None, false), Theme::Light);
+        assert_eq!(Theme::System.toggled(None, true), Theme::Light);
+        assert_eq!(Theme::System.toggled(None, false), Theme::Dark);
+
+        let night = CustomTheme {
+            name: String::from("Night"),
+            base: Theme::Dark,
+            colors: BTreeMap::new(),
+        };
+        assert_eq!(Theme::Custom.toggled(Some(&night), false), Theme::Light);
+    }
+}
@@ -1,417 +1,899 @@
 use std::{cell::RefCell, convert::TryFrom};
 use std::{rc::Rc, vec};
 
-use js_sys::Array;
+use js_sys::{Array, Function, Map, Promise, Reflect};
 use wasm_bindgen::JsCast;
 use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
 use web_sys::{
     MessageEvent, RtcConfiguration, RtcDataChannel, RtcDataChannelInit, RtcDataChannelState,
-    RtcIceCandidateInit, RtcIceServer, RtcPeerConnection, RtcPeerConnectionIceEvent, RtcSdpType,
-    RtcSessionDescription, RtcSessionDescriptionInit, RtcSignalingState, WebSocket,
+    RtcIceCandidateInit, RtcIceServer, RtcIceTransportPolicy, RtcOfferOptions, RtcPeerConnection, RtcPeerConnectionIceEvent,
+    RtcSessionDescriptionInit,
 };
 use yew::agent::{Dispatched, Dispatcher};
+use yew::Callback;
 
-use crate::{components::chat_message::{ChatMessage, SenderType}, utils::{
+use crate::{components::{chat_message::{ChatMessage, SenderType}, toast::Notification}, utils::{
+    callbacks::CallbackRegistry,
+    connection_state::ConnectionState,
+    error::{js_error_message, CustomError},
+    ice_candidate::{CandidatePolicy, IceCandidate},
     participants::Participants,
-    socket::{Candidate, Room, SDPMessage, SignalingMessage, SocketMessage},
+    stats::{ConnectionStats, RawStats},
+    session_code,
+    settings::Settings,
+    socket::{Candidate, Room, SdpType, SDPMessage, SignalingMessage, SocketMessage},
 }};
-use crate::event_bus::{EventBus, Request};
+use crate::event_bus::{self, EventBus, Request};
+use crate::negotiation::{Command, Event, Negotiation};
+use crate::peer_agent::ConversationId;
+use crate::signaling::{SignalingConfig, SignalingTransport, TransportState};
 
 type SingleArgClosure<T> = Closure<dyn FnMut(T)>;
 type BoxDynValue<T> = Box<dyn FnMut(T)>;
 
+pub const DEFAULT_MAX_ICE_RESTART_ATTEMPTS: u8 = 3;
+// Browsers often recover from "disconnected" on their own, give them some time first
+const DISCONNECTED_GRACE_PERIOD_MS: i32 = 5_000;
+const ICE_RESTART_TIMEOUT_MS: i32 = 10_000;
+
+// Both peers create the same negotiated data channel when there is no signaling server
+const MANUAL_DATA_CHANNEL_LABEL: &str = "prust";
+const MANUAL_DATA_CHANNEL_ID: u16 = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SignalingMode {
+    Server,
+    // Descriptions are exchanged by the users themselves, with session codes
+    ManualOffer,
+    ManualAnswer,
+}
+
+pub struct WebRTCConfig {
+    /// Tells the conversation of the connection state events apart
+    pub conversation: ConversationId,
+    /// Of the offerer through the signaling server, the answerer waits as long for them
+    pub max_ice_restart_attempts: u8,
+    pub signaling: SignalingConfig,
+    /// For peers without UI: the received messages go here and nothing is sent to the event bus
+    pub on_message: Option<Callback<String>>,
+    /// Receives the events instead of the event bus, the received messages included
+    pub on_event: Option<Callback<event_bus::Event>>,
+}
+
+impl Default for WebRTCConfig {
+    fn default() -> Self {
+        Self {
+            conversation: 0,
+            max_ice_restart_attempts: DEFAULT_MAX_ICE_RESTART_ATTEMPTS,
+            signaling: SignalingConfig::default(),
+            on_message: None,
+            on_event: None,
+        }
+    }
+}
+
 pub struct WebRTC {
     // https://rustwasm.github.io/wasm-bindgen/api/web_sys/struct.RtcPeerConnection.html
-    pub connection: RtcPeerConnection,
-    room: Option<String>,
-    signaling_channel_opened: bool,
-    is_negotiating: bool,
-    candidates_buffer: Vec<RtcIceCandidateInit>,
+    // Created by each connection, none before the first one
+    connection: Option<RtcPeerConnection>,
+    negotiation: Negotiation,
+    // Bumped with every new peer connection, results of the previous one are ignored
+    generation: u32,
     data_channel: Option<RtcDataChannel>,
-    socket: WebSocket,
-    event_bus: Dispatcher<EventBus>,
+    // Opened by the first connection through the signaling, the manual modes do without
+    signaling: Option<Box<dyn SignalingTransport>>,
+    event_bus: Option<Dispatcher<EventBus>>,
+    // Closures of the handlers of the current connection, freed when it is closed
+    callbacks: CallbackRegistry,
+    recovery_check: Option<Function>,
+    state: ConnectionState,
+    config: WebRTCConfig,
+    ice_restart_attempts: u8,
+    ice_restart_abandoned: bool,
+    recovery_timeout: Option<i32>,
+    candidate_policy: CandidatePolicy,
+    signaling_mode: SignalingMode,
+    session_code_callback: Option<Callback<String>>,
 }
 
 impl WebRTC {
-    pub fn new() -> Self {
-        let mut ice_server = RtcIceServer::new();
-        ice_server.urls(&JsValue::from_str("stun:stun.l.google.com:19302"));
-
-        let mut configuration = RtcConfiguration::new();
-        configuration.ice_servers(&Array::of1(&ice_server));
-        let peer_connection = RtcPeerConnection::new_with_configuration(&configuration)
-            .expect("Cannot create a Peer Connection");
-
-        let socket = WebSocket::new("wss://glacial-beyond-33808.herokuapp.com").unwrap();
-
-        // Is equivalent to onConnect in JS
-        let onopen_callback: SingleArgClosure<JsValue> = Closure::wrap(Box::new(move |_| {
-            log::info!("socket opened");
-        }));
-        socket.set_onopen(Some(onopen_callback.as_ref().unchecked_ref()));
-        onopen_callback.forget();
-
-        let onclose_callback: SingleArgClosure<JsValue> = Closure::wrap(Box::new(move |_| {
-            log::info!("socket closed");
-        }));
-        socket.set_onclose(Some(onclose_callback.as_ref().unchecked_ref()));
-        onclose_callback.forget();
+    pub fn new(config: WebRTCConfig) -> Self {
+        let event_bus = match (&config.on_message, &config.on_event) {
+            (None, None) => Some(EventBus::dispatcher()),
+            _ => None,
+        };
 
         Self {
-            connection: peer_connection,
-            room: None,
-            is_negotiating: false,
-            candidates_buffer: vec![],
-            signaling_channel_opened: false,
+            connection: None,
+            negotiation: Negotiation::new(false),
+            generation: 0,
             data_channel: None,
-            socket,
-            event_bus: EventBus::dispatcher(),
+            signaling: None,
+            event_bus,
+            callbacks: CallbackRegistry::default(),
+            recovery_check: None,
+            state: ConnectionState::New,
+            config,
+            ice_restart_attempts: 0,
+            ice_restart_abandoned: false,
+            recovery_timeout: None,
+            candidate_policy: CandidatePolicy::default(),
+            signaling_mode: SignalingMode::Server,
+            session_code_callback: None,
         }
     }
 
-    pub fn connect(web_rtc: Rc<RefCell<WebRTC>>, participants: Participants) {
+    fn create_peer_connection(candidate_policy: CandidatePolicy) -> Result<RtcPeerConnection, CustomError> {
+        let ice_servers = Array::new();
+        for server in Settings::load().ice_servers {
+            let ice_server = RtcIceServer::new();
+            ice_server.set_urls(&JsValue::from_str(&server.urls));
+            if let (Some(username), Some(credential)) = (&server.username, &server.credential) {
+                ice_server.set_username(username);
+                ice_server.set_credential(credential);
+            }
+            ice_servers.push(&ice_server);
+        }
+
+        let configuration = RtcConfiguration::new();
+        configuration.set_ice_servers(&ice_servers);
+        if candidate_policy == CandidatePolicy::RelayOnly {
+            // Do not even gather the other candidates
+            configuration.set_ice_transport_policy(RtcIceTransportPolicy::Relay);
+        }
+        RtcPeerConnection::new_with_configuration(&configuration)
+            .map_err(|err| CustomError::NegotiationError(js_error_message(&err)))
+    }
+
+    pub fn connect(
+        web_rtc: Rc<RefCell<WebRTC>>,
+        participants: Participants,
+        candidate_policy: CandidatePolicy,
+    ) -> Result<(), CustomError> {
+        let polite = Negotiation::is_polite(&participants.user_from, &participants.user_to);
+        WebRTC::join(web_rtc, participants, candidate_policy, polite)
+    }
+
+    /// Joins the shared room of `participants`, the names of the users cannot tell the roles apart:
+    /// the host, who created the room, is the impolite peer.
+    pub fn join_room(
+        web_rtc: Rc<RefCell<WebRTC>>,
+        participants: Participants,
+        candidate_policy: CandidatePolicy,
+        host: bool,
+    ) -> Result<(), CustomError> {
+        WebRTC::join(web_rtc, participants, candidate_policy, !host)
+    }
+
+    fn join(
+        web_rtc: Rc<RefCell<WebRTC>>,
+        participants: Participants,
+        candidate_policy: CandidatePolicy,
+        polite: bool,
+    ) -> Result<(), CustomError> {
+        if web_rtc.as_ref().borrow().signaling.is_none() {
+            let signaling = web_rtc.as_ref().borrow().config.signaling.connect()?;
+            web_rtc.as_ref().borrow_mut().signaling = Some(signaling);
+        }
+        WebRTC::prepare_connection(&web_rtc, SignalingMode::Server, candidate_policy, polite)?;
+
         let on_message_callback = WebRTC::get_socket_message_callback(&web_rtc);
-        let on_ice_candidate_callback = WebRTC::get_on_ice_candidate_callback(&web_rtc);
-        let on_signaling_callback = WebRTC::get_on_signaling_callback(&web_rtc);
-        let on_negotiation_needed_callback = WebRTC::get_negotiation_needed_callback(&web_rtc);
-
-        web_rtc.as_ref()
-            .borrow()
-            .socket
-            .set_onmessage(Some(on_message_callback.as_ref().unchecked_ref()));
-        on_message_callback.forget();
-
-        web_rtc
-            .as_ref()
-            .borrow()
-            .connection
-            .set_onicecandidate(Some(on_ice_candidate_callback.as_ref().unchecked_ref()));
-        on_ice_candidate_callback.forget();
-
-        web_rtc
-            .as_ref()
-            .borrow()
-            .connection
-            .set_onsignalingstatechange(Some(on_signaling_callback.as_ref().unchecked_ref()));
-        on_signaling_callback.forget();
-
-        web_rtc
-            .as_ref()
-            .borrow()
-            .connection
-            .set_onnegotiationneeded(Some(
-                on_negotiation_needed_callback.as_ref().unchecked_ref(),
-            ));
-        on_negotiation_needed_callback.forget();
+        let on_transport_state_callback = WebRTC::get_transport_state_callback(&web_rtc);
+        let mut borrow_mut = web_rtc.as_ref().borrow_mut();
+        if let Some(signaling) = &borrow_mut.signaling {
+            signaling.subscribe(Some(on_message_callback));
+            signaling.subscribe_state(Some(on_transport_state_callback));
+        }
 
         // Send connect message in socket
-        let new_user_message = SocketMessage::NewUser { content: participants };
-        let json_new_user_message = serde_json::to_string(&new_user_message).unwrap();
-        let send_res = web_rtc
-            .as_ref()
-            .borrow()
-            .socket
-            .send_with_str(json_new_user_message.as_ref());
-        match send_res {
-            Ok(_) => (),
-            Err(ex) => log::error!("Could not connect to websocket {:?}", ex),
+        borrow_mut.send_socket_message(&SocketMessage::NewUser { content: participants });
+        borrow_mut.set_state(ConnectionState::Signaling);
+        Ok(())
+    }
+
+    /// Starts a session without signaling server: `on_session_code` receives the complete offer,
+    /// encoded, once every ICE candidate has been gathered.
+    pub fn create_manual_offer(
+        web_rtc: Rc<RefCell<WebRTC>>,
+        candidate_policy: CandidatePolicy,
+        on_session_code: Callback<String>,
+    ) -> Result<(), CustomError> {
+        WebRTC::prepare_connection(&web_rtc, SignalingMode::ManualOffer, candidate_policy, false)?;
+        web_rtc.as_ref().borrow_mut().session_code_callback = Some(on_session_code);
+        // Creating the data channel triggers the negotiation
+        WebRTC::open_data_channel(&web_rtc, MANUAL_DATA_CHANNEL_LABEL, MANUAL_DATA_CHANNEL_ID);
+        Ok(())
+    }
+
+    /// Answers an offer created by `create_manual_offer`, `on_session_code` receives the encoded answer.
+    pub fn accept_manual_offer(
+        web_rtc: Rc<RefCell<WebRTC>>,
+        session_code: &str,
+        candidate_policy: CandidatePolicy,
+        on_session_code: Callback<String>,
+    ) -> Result<(), CustomError> {
+        let offer = session_code::decode(session_code)?;
+        if offer.type_ != SdpType::Offer {
+            return Err(CustomError::SessionCodeError(String::from("it is not an invitation")));
         }
+
+        WebRTC::prepare_connection(&web_rtc, SignalingMode::ManualAnswer, candidate_policy, true)?;
+        web_rtc.as_ref().borrow_mut().session_code_callback = Some(on_session_code);
+        WebRTC::open_data_channel(&web_rtc, MANUAL_DATA_CHANNEL_LABEL, MANUAL_DATA_CHANNEL_ID);
+        WebRTC::handle_sdp_message(&web_rtc, offer, 0);
+        Ok(())
     }
 
-    fn get_socket_message_callback(web_rtc: &Rc<RefCell<WebRTC>>) -> Closure<dyn FnMut(MessageEvent)> {
-        let on_message_clone = web_rtc.clone();
-        Closure::wrap(Box::new(move |message: MessageEvent| {
-            let message = SocketMessage::try_from(message);
-            match message {
-                Ok(parsed) => WebRTC::handle_socket_message(on_message_clone.clone(), parsed),
-                Err(error) => log::error!("Oh No: {:?}", error),
-            };
-        }))
+    /// Completes a session started with `create_manual_offer`
+    pub fn accept_manual_answer(web_rtc: Rc<RefCell<WebRTC>>, session_code: &str) -> Result<(), CustomError> {
+        let answer = session_code::decode(session_code)?;
+        if answer.type_ != SdpType::Answer {
+            return Err(CustomError::SessionCodeError(String::from("it is not an answer")));
+        }
+        WebRTC::handle_sdp_message(&web_rtc, answer, 0);
+        Ok(())
+    }
+
+    // Replaces the unused connection to follow the policy and listens to it
+    fn prepare_connection(
+        web_rtc: &Rc<RefCell<WebRTC>>,
+        signaling_mode: SignalingMode,
+        candidate_policy: CandidatePolicy,
+        polite: bool,
+    ) -> Result<(), CustomError> {
+        {
+            let mut borrow_mut = web_rtc.as_ref().borrow_mut();
+            borrow_mut.signaling_mode = signaling_mode;
+            borrow_mut.candidate_policy = candidate_policy;
+            borrow_mut.connection = Some(WebRTC::create_peer_connection(candidate_policy)?);
+            borrow_mut.negotiation = Negotiation::new(polite);
+            borrow_mut.generation = borrow_mut.generation.wrapping_add(1);
+        }
+
+        let on_ice_candidate_callback = WebRTC::get_on_ice_candidate_callback(web_rtc);
+        let on_negotiation_needed_callback = WebRTC::get_negotiation_needed_callback(web_rtc);
+        let on_recovery_check_callback = WebRTC::get_recovery_check_callback(web_rtc);
+        let on_ice_connection_state_callback = WebRTC::get_peer_state_callback(web_rtc);
+        let on_connection_state_callback = WebRTC::get_peer_state_callback(web_rtc);
+
+        let mut borrow_mut = web_rtc.as_ref().borrow_mut();
+        // The replaced connection had no handler left, its closures can go
+        borrow_mut.callbacks.clear();
+        let on_ice_candidate = borrow_mut.callbacks.register(on_ice_candidate_callback);
+        let on_negotiation_needed = borrow_mut.callbacks.register(on_negotiation_needed_callback);
+        let on_ice_connection_state = borrow_mut.callbacks.register(on_ice_connection_state_callback);
+        let on_connection_state = borrow_mut.callbacks.register(on_connection_state_callback);
+        let recovery_check = borrow_mut.callbacks.register(on_recovery_check_callback);
+        borrow_mut.recovery_check = Some(recovery_check);
+
+        let connection = match &borrow_mut.connection {
+            Some(connection) => connection,
+            None => return Ok(()),
+        };
+        connection.set_onicecandidate(Some(&on_ice_candidate));
+        connection.set_onnegotiationneeded(Some(&on_negotiation_needed));
+        connection.set_oniceconnectionstatechange(Some(&on_ice_connection_state));
+        // connectionstatechange is missing from web-sys and from some browsers
+        let _ = Reflect::set(
+            connection,
+            &JsValue::from_str("onconnectionstatechange"),
+            &on_connection_state,
+        );
+        Ok(())
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    fn publish(&mut self, event: event_bus::Event) {
+        if let Some(on_event) = &self.config.on_event {
+            on_event.emit(event);
+        } else if let Some(event_bus) = &mut self.event_bus {
+            event_bus.send(Request::Publish(event));
+        }
+    }
+
+    // Logs the error and tells the user about it
+    fn report(&mut self, error: CustomError) {
+        log::error!("{}", error);
+        self.publish(event_bus::Event::Notification(Notification::from(&error)));
     }
 
-    fn get_on_signaling_callback(web_rtc: &Rc<RefCell<WebRTC>>) -> SingleArgClosure<MessageEvent> {
-        let webrtc_signaling_clone = web_rtc.clone();
-        Closure::wrap(Box::new(move |_: MessageEvent| {
-            let new_value = webrtc_signaling_clone
+    fn set_state(&mut self, state: ConnectionState) {
+        if self.state != state {
+            log::info!("Connection state: {} -> {}", self.state, state);
+            self.state = state;
+            self.publish(event_bus::Event::ConnectionState(self.config.conversation, state));
+        }
+        if state == ConnectionState::Connected {
+            self.ice_restart_attempts = 0;
+            self.ice_restart_abandoned = false;
+            self.cancel_recovery_check();
+        }
+    }
+
+    fn on_peer_state_changed(web_rtc: &Rc<RefCell<WebRTC>>) {
+        let state = {
+            let mut borrow_mut = web_rtc.as_ref().borrow_mut();
+            let state = match borrow_mut.peer_state() {
+                Some(state) => state,
+                None => return,
+            };
+            // The chat is only usable once the data channel is open
+            let data_channel_open = borrow_mut
+                .data_channel
                 .as_ref()
-                .borrow()
-                .connection
-                .signaling_state()
-                == RtcSignalingState::Stable;
-            webrtc_signaling_clone
+                .is_some_and(|data_channel| data_channel.ready_state() == RtcDataChannelState::Open);
+            if state != ConnectionState::Connected || data_channel_open {
+                borrow_mut.set_state(state);
+            }
+            state
+        };
+
+        match state {
+            ConnectionState::Disconnected => web_rtc
                 .as_ref()
                 .borrow_mut()
-                .is_negotiating = new_value;
+                .schedule_recovery_check(DISCONNECTED_GRACE_PERIOD_MS),
+            ConnectionState::Failed => WebRTC::restart_ice(web_rtc),
+            _ => (),
+        }
+    }
+
+    fn restart_ice(web_rtc: &Rc<RefCell<WebRTC>>) {
+        {
+            let mut borrow_mut = web_rtc.as_ref().borrow_mut();
+            if borrow_mut.ice_restart_abandoned {
+                return;
+            }
+            // Without signaling server, there is no way to send the new offer.
+            // The browser may still recover from "disconnected" on its own.
+            if borrow_mut.signaling_mode != SignalingMode::Server {
+                if borrow_mut.state == ConnectionState::Failed {
+                    borrow_mut.abandon_ice_restart(CustomError::ConnectionLostError(String::from(
+                        "no ICE restart without signaling server",
+                    )));
+                }
+                return;
+            }
+            if borrow_mut.ice_restart_attempts >= borrow_mut.config.max_ice_restart_attempts {
+                let attempts = borrow_mut.ice_restart_attempts;
+                borrow_mut.abandon_ice_restart(CustomError::IceError(format!(
+                    "Giving up ICE restart after {} attempts",
+                    attempts
+                )));
+                return;
+            }
+            borrow_mut.ice_restart_attempts += 1;
+            borrow_mut.schedule_recovery_check(ICE_RESTART_TIMEOUT_MS);
+            // The offerer restarts, the answerer waits for its new offer and gives up with it
+            if !borrow_mut.negotiation.is_offerer() {
+                log::warn!(
+                    "Waiting for the ICE restart of the offerer {}/{}",
+                    borrow_mut.ice_restart_attempts,
+                    borrow_mut.config.max_ice_restart_attempts
+                );
+                return;
+            }
+            log::warn!(
+                "ICE restart attempt {}/{}",
+                borrow_mut.ice_restart_attempts,
+                borrow_mut.config.max_ice_restart_attempts
+            );
+        }
+        WebRTC::dispatch(web_rtc, Event::RestartIce);
+    }
+
+    fn abandon_ice_restart(&mut self, error: CustomError) {
+        self.ice_restart_abandoned = true;
+        self.cancel_recovery_check();
+        self.set_state(ConnectionState::Failed);
+        self.report(error);
+    }
+
+    fn schedule_recovery_check(&mut self, delay_ms: i32) {
+        self.cancel_recovery_check();
+        if let (Some(window), Some(recovery_check)) = (web_sys::window(), &self.recovery_check) {
+            match window.set_timeout_with_callback_and_timeout_and_arguments_0(recovery_check, delay_ms) {
+                Ok(handle) => self.recovery_timeout = Some(handle),
+                Err(err) => log::error!("Could not schedule the connection recovery {:?}", err),
+            }
+        }
+    }
+
+    fn cancel_recovery_check(&mut self) {
+        if let (Some(handle), Some(window)) = (self.recovery_timeout.take(), web_sys::window()) {
+            window.clear_timeout_with_handle(handle);
+        }
+    }
+
+    // Prefer connectionState, which also accounts for DTLS, and fall back on iceConnectionState
+    fn peer_state(&self) -> Option<ConnectionState> {
+        let connection = self.connection.as_ref()?;
+        Reflect::get(connection, &JsValue::from_str("connectionState"))
+            .ok()
+            .and_then(|connection_state| connection_state.as_string())
+            .or_else(|| {
+                Reflect::get(connection, &JsValue::from_str("iceConnectionState"))
+                    .ok()
+                    .and_then(|ice_state| ice_state.as_string())
+            })
+            .and_then(|peer_state| ConnectionState::from_peer_state(&peer_state))
+    }
+
+    /// Leaves the current room and tears the peer connection down.
+    /// `connect` can be called again, it creates a new `RtcPeerConnection`.
+    pub fn disconnect(web_rtc: Rc<RefCell<WebRTC>>) {
+        let mut borrow_mut = web_rtc.as_ref().borrow_mut();
+        if let Some(room) = borrow_mut.negotiation.room().map(String::from) {
+            log::info!("Leaving room {}", room);
+            borrow_mut.send_socket_message(&SocketMessage::SignalMessageFromClient {
+                content: SignalingMessage::UserLeft,
+            });
+            borrow_mut.send_socket_message(&SocketMessage::LeaveRoom {
+                content: Room { room },
+            });
+        }
+        borrow_mut.close_connection();
+    }
+
+    fn close_connection(&mut self) {
+        if let Some(signaling) = &self.signaling {
+            signaling.subscribe(None);
+            signaling.subscribe_state(None);
+        }
+        if let Some(connection) = &self.connection {
+            connection.set_onicecandidate(None);
+            connection.set_onnegotiationneeded(None);
+            connection.set_oniceconnectionstatechange(None);
+            let _ = Reflect::set(connection, &JsValue::from_str("onconnectionstatechange"), &JsValue::NULL);
+        }
+        if let Some(data_channel) = self.data_channel.take() {
+            data_channel.set_onmessage(None);
+            data_channel.set_onopen(None);
+            data_channel.set_onclose(None);
+            data_channel.close();
+        }
+        // The next connection creates its own, a session does not hold one while idle
+        if let Some(connection) = self.connection.take() {
+            connection.close();
+        }
+        self.cancel_recovery_check();
+
+        // Every handler is unregistered, the closures can now be freed
+        self.recovery_check = None;
+        self.callbacks.clear();
+
+        self.negotiation = Negotiation::new(false);
+        self.generation = self.generation.wrapping_add(1);
+        self.ice_restart_attempts = 0;
+        self.ice_restart_abandoned = false;
+        self.signaling_mode = SignalingMode::Server;
+        self.session_code_callback = None;
+        self.set_state(ConnectionState::Closed);
+    }
+
+    // Without signaling server, descriptions are handed to the user once ICE gathering is complete
+    fn send_signaling_message(&self, signaling_message: SignalingMessage) {
+        if self.signaling_mode == SignalingMode::Server {
+            self.send_socket_message(&SocketMessage::SignalMessageFromClient {
+                content: signaling_message,
+            });
+        }
+    }
+
+    fn send_socket_message(&self, socket_message: &SocketMessage) {
+        if let Some(signaling) = &self.signaling {
+            signaling.send(socket_message);
+        }
+    }
+
+    fn get_socket_message_callback(web_rtc: &Rc<RefCell<WebRTC>>) -> Callback<SocketMessage> {
+        let on_message_clone = web_rtc.clone();
+        Callback::from(move |message: SocketMessage| {
+            WebRTC::handle_socket_message(on_message_clone.clone(), message);
+        })
+    }
+
+    fn get_transport_state_callback(web_rtc: &Rc<RefCell<WebRTC>>) -> Callback<TransportState> {
+        let transport_state_clone = web_rtc.clone();
+        Callback::from(move |transport_state: TransportState| {
+            log::info!("Signaling transport: {:?}", transport_state);
+            let mut borrow_mut = transport_state_clone.as_ref().borrow_mut();
+            // Once connected, the peers do not need the signaling anymore
+            let still_signaling = borrow_mut.state == ConnectionState::Signaling
+                || borrow_mut.state == ConnectionState::Connecting;
+            if transport_state == TransportState::Closed && still_signaling {
+                borrow_mut.report(CustomError::SignalingError(String::from(
+                    "Lost the connection with the signaling server",
+                )));
+            }
+        })
+    }
+
+    fn get_peer_state_callback(web_rtc: &Rc<RefCell<WebRTC>>) -> SingleArgClosure<JsValue> {
+        let peer_state_clone = web_rtc.clone();
+        Closure::wrap(Box::new(move |_: JsValue| {
+            WebRTC::on_peer_state_changed(&peer_state_clone);
+        }))
+    }
+
+    fn get_recovery_check_callback(web_rtc: &Rc<RefCell<WebRTC>>) -> SingleArgClosure<JsValue> {
+        let recovery_clone = web_rtc.clone();
+        Closure::wrap(Box::new(move |_: JsValue| {
+            let still_broken = {
+                let mut borrow_mut = recovery_clone.as_ref().borrow_mut();
+                borrow_mut.recovery_timeout = None;
+                borrow_mut.state != ConnectionState::Connected
+            };
+            if still_broken {
+                WebRTC::restart_ice(&recovery_clone);
+            }
         }))
     }
 
     fn get_on_ice_candidate_callback(web_rtc: &Rc<RefCell<WebRTC>>) -> SingleArgClosure<RtcPeerConnectionIceEvent> {
         let on_ice_cloned = web_rtc.clone();
         Closure::wrap(Box::new(move |event: RtcPeerConnectionIceEvent| {
-            log::info!("ICE: Send ice_candidate to signaling server");
-            if let Some(candidate) = event.candidate() {
-                if !candidate.candidate().is_empty() {
-                    let signal_message_from_client = SocketMessage::SignalMessageFromClient {
-                        content: SignalingMessage::ICECandidate {
-                            message: Candidate {
-                                candidate: candidate.candidate(),
-                                sdp_mid: candidate.sdp_mid().unwrap(),
-                                sdp_m_line_index: candidate.sdp_m_line_index().unwrap(),
-                            },
-                        },
-                    };
-
-                    let json_from_client_message =
-                        serde_json::to_string(&signal_message_from_client).unwrap();
-                    let send_res = on_ice_cloned
-                        .as_ref()
-                        .borrow()
-                        .socket
-                        .send_with_str(json_from_client_message.as_ref());
-                    if let Err(ex) = send_res {
-                        log::error!("Could not execute ice candidate callback {:?}", ex)
-                    }
-                }
+            let candidate = match event.candidate() {
+                Some(candidate) => candidate,
+                // A null candidate means that ICE gathering is complete
+                None => return WebRTC::emit_session_code(&on_ice_cloned),
+            };
+            if candidate.candidate().is_empty() {
+                return;
             }
+            let shared = on_ice_cloned.as_ref().borrow().candidate_policy.apply_line(&candidate.candidate());
+            let shared = match shared {
+                Some(shared) => shared,
+                None => {
+                    log::info!("ICE: Candidate hidden by the privacy policy");
+                    return;
+                }
+            };
+
+            log::info!("ICE: Send ice_candidate to signaling server");
+            WebRTC::dispatch(
+                &on_ice_cloned,
+                Event::LocalCandidate(Candidate {
+                    candidate: shared,
+                    sdp_mid: candidate.sdp_mid().unwrap_or_default(),
+                    sdp_m_line_index: candidate.sdp_m_line_index().unwrap_or_default(),
+                }),
+            );
         }))
     }
 
-    fn get_negotiation_needed_callback(web_rtc: &Rc<RefCell<WebRTC>>) -> SingleArgClosure<JsValue> {
-        let sdp_clone = web_rtc.clone();
-        let send_sdp_callback: SingleArgClosure<JsValue> =
-            Closure::wrap(Box::new(move |_: JsValue| {
-                log::info!("Step 3: On negotiation needed, send offer to signaling server");
-                let session_description = sdp_clone
-                    .as_ref()
-                    .borrow()
-                    .connection
-                    .local_description()
-                    .unwrap();
-                let message_to_send = SocketMessage::SignalMessageFromClient {
-                    content: SignalingMessage::SDP {
-                        message: SDPMessage::try_from(session_description).unwrap(),
-                    },
-                };
-                let message_to_send = serde_json::to_string(&message_to_send).unwrap();
-                match sdp_clone
-                    .as_ref()
-                    .borrow()
-                    .socket
-                    .send_with_str(&message_to_send)
-                {
-                    Ok(_) => (),
-                    Err(err) => log::error!("Error in sdp callback {:?}", err),
-                };
-            }));
+    // Emitted without borrowing, the receiver may start another connection right away
+    fn emit_session_code(web_rtc: &Rc<RefCell<WebRTC>>) {
+        let (on_session_code, sdp_message) = {
+            let borrowed = web_rtc.as_ref().borrow();
+            (borrowed.session_code_callback.clone(), borrowed.local_sdp_message())
+        };
+        if let (Some(on_session_code), Some(sdp_message)) = (on_session_code, sdp_message) {
+            match session_code::encode(&sdp_message) {
+                Ok(code) => on_session_code.emit(code),
+                Err(err) => web_rtc.as_ref().borrow_mut().report(err),
+            }
+        }
+    }
 
-        let on_negotiation_success_clone = web_rtc.clone();
-        let negotiation_success_callback: SingleArgClosure<JsValue> = Closure::wrap(Box::new(move |descriptor: JsValue| {
-            log::info!("Step 2: On negotiation needed, set_local_description");
-            let description_init = RtcSessionDescriptionInit::try_from(descriptor).unwrap();
-            let _ = on_negotiation_success_clone
-                .as_ref()
-                .borrow()
-                .connection
-                .set_local_description(&description_init)
-                .then(&send_sdp_callback);
-        }));
+    // The commands of the negotiation may come after the connection was closed
+    fn peer_connection(&self) -> Option<RtcPeerConnection> {
+        if self.connection.is_none() {
+            log::warn!("Negotiation: the peer connection is closed");
+        }
+        self.connection.clone()
+    }
+
+    // Candidates gathered before the description is sent are embedded in the SDP
+    fn local_sdp_message(&self) -> Option<SDPMessage> {
+        let session_description = self.connection.as_ref()?.local_description()?;
+        match SDPMessage::try_from(session_description) {
+            Ok(mut sdp_message) => {
+                sdp_message.sdp = self.candidate_policy.filter_sdp(&sdp_message.sdp);
+                Some(sdp_message)
+            }
+            Err(err) => {
+                log::error!("Could not read the local description: {}", err);
+                None
+            }
+        }
+    }
 
+    fn get_negotiation_needed_callback(web_rtc: &Rc<RefCell<WebRTC>>) -> SingleArgClosure<JsValue> {
         let on_negotiation_needed_clone = web_rtc.clone();
         Closure::wrap(Box::new(move |_: JsValue| {
-            let mut borrow_mut = on_negotiation_needed_clone.as_ref().borrow_mut();
-            if !borrow_mut.is_negotiating {
-                log::info!("Step 1: On negotiation needed, create offer");
-                borrow_mut.is_negotiating = true;
-
-                let print_error_callback =
-                    Closure::wrap(Box::new(|err| log::error!("{:?}", err)) as BoxDynValue<JsValue>);
-                let _ = borrow_mut
-                    .connection
-                    .create_offer()
-                    .then(&negotiation_success_callback)
-                    .catch(&print_error_callback);
+            // The answerer of a manual session has no way to send an offer
+            if on_negotiation_needed_clone.as_ref().borrow().signaling_mode == SignalingMode::ManualAnswer {
+                return;
             }
+            WebRTC::dispatch(&on_negotiation_needed_clone, Event::NegotiationNeeded);
         }))
     }
 
-    pub fn send_webrtc_message(web_rtc: Rc<RefCell<WebRTC>>, message: &str) {
-        if let Some(data_channel) = &web_rtc.as_ref().borrow().data_channel {
-            if data_channel.ready_state() == RtcDataChannelState::Open {
-                match data_channel.send_with_str(message) {
-                    Ok(_) => (),
-                    Err(err) => log::error!("Could not send message {:?}", err),
+    // Feeds the negotiation and runs what it decides, never while borrowed
+    fn dispatch(web_rtc: &Rc<RefCell<WebRTC>>, event: Event) {
+        let commands = web_rtc.as_ref().borrow_mut().negotiation.handle(event);
+        for command in commands {
+            WebRTC::execute(web_rtc, command);
+        }
+    }
+
+    fn execute(web_rtc: &Rc<RefCell<WebRTC>>, command: Command) {
+        match command {
+            Command::OpenDataChannel { label, id } => WebRTC::open_data_channel(web_rtc, &label, id),
+            Command::CreateOffer { ice_restart } => {
+                log::info!("Negotiation: create offer (ICE restart: {})", ice_restart);
+                let connection = match web_rtc.as_ref().borrow().peer_connection() {
+                    Some(connection) => connection,
+                    None => return,
+                };
+                let offer = if ice_restart {
+                    let offer_options = RtcOfferOptions::new();
+                    offer_options.set_ice_restart(true);
+                    connection.create_offer_with_rtc_offer_options(&offer_options)
+                } else {
+                    connection.create_offer()
+                };
+                WebRTC::set_local_description(web_rtc, SdpType::Offer, offer);
+            }
+            Command::CreateAnswer => {
+                log::info!("Negotiation: create answer");
+                let answer = match web_rtc.as_ref().borrow().peer_connection() {
+                    Some(connection) => connection.create_answer(),
+                    None => return,
+                };
+                WebRTC::set_local_description(web_rtc, SdpType::Answer, answer);
+            }
+            Command::SetRemoteDescription(sdp_message) => WebRTC::set_remote_description(web_rtc, sdp_message),
+            Command::AddIceCandidate(candidate) => WebRTC::add_ice_candidate(web_rtc, candidate),
+            Command::Send(signaling_message) => web_rtc.as_ref().borrow().send_signaling_message(signaling_message),
+            Command::Close => {
+                log::info!("The other peer left the room");
+                web_rtc.as_ref().borrow_mut().close_connection();
+            }
+        }
+    }
+
+    // `description` is the promise of createOffer or createAnswer
+    fn set_local_description(web_rtc: &Rc<RefCell<WebRTC>>, type_: SdpType, description: Promise) {
+        let web_rtc = web_rtc.clone();
+        let generation = web_rtc.as_ref().borrow().generation;
+        spawn_local(async move {
+            let result = async {
+                let description = JsFuture::from(description).await?;
+                let description_init = RtcSessionDescriptionInit::from(description);
+                let connection = web_rtc.as_ref().borrow().peer_connection();
+                match connection {
+                    Some(connection) => JsFuture::from(connection.set_local_description(&description_init)).await,
+                    None => Err(JsValue::from_str("The peer connection is closed")),
+                }
+            }
+            .await;
+            if web_rtc.as_ref().borrow().generation != generation {
+                return;
+            }
+            let event = match result {
+                Ok(_) => match web_rtc.as_ref().borrow().local_sdp_message() {
+                    Some(sdp_message) => Event::LocalDescriptionSet(sdp_message),
+                    None => Event::LocalDescriptionFailed,
+                },
+                // An offer fails when the one of the other peer is accepted instead, an answer should not
+                Err(err) if type_ == SdpType::Offer => {
+                    log::warn!("Could not set the local offer: {}", js_error_message(&err));
+                    Event::LocalDescriptionFailed
+                }
+                Err(err) => {
+                    web_rtc.as_ref().borrow_mut().report(CustomError::NegotiationError(format!(
+                        "Could not set the local {:?}: {}",
+                        type_,
+                        js_error_message(&err)
+                    )));
+                    Event::LocalDescriptionFailed
+                }
+            };
+            WebRTC::dispatch(&web_rtc, event);
+        });
+    }
+
+    fn set_remote_description(web_rtc: &Rc<RefCell<WebRTC>>, sdp_message: SDPMessage) {
+        let description_init = match RtcSessionDescriptionInit::try_from(sdp_message) {
+            Ok(description_init) => description_init,
+            Err(err) => {
+                web_rtc.as_ref().borrow_mut().report(err);
+                return WebRTC::dispatch(web_rtc, Event::RemoteDescriptionFailed);
+            }
+        };
+        let set_remote = match web_rtc.as_ref().borrow().peer_connection() {
+            Some(connection) => connection.set_remote_description(&description_init),
+            None => return,
+        };
+        let web_rtc = web_rtc.clone();
+        let generation = web_rtc.as_ref().borrow().generation;
+        spawn_local(async move {
+            let result = JsFuture::from(set_remote).await;
+            if web_rtc.as_ref().borrow().generation != generation {
+                return;
+            }
+            let event = match result {
+                Ok(_) => Event::RemoteDescriptionSet,
+                Err(err) => {
+                    web_rtc.as_ref().borrow_mut().report(CustomError::NegotiationError(format!(
+                        "Could not set the remote description: {}",
+                        js_error_message(&err)
+                    )));
+                    Event::RemoteDescriptionFailed
                 }
+            };
+            WebRTC::dispatch(&web_rtc, event);
+        });
+    }
+
+    fn add_ice_candidate(web_rtc: &Rc<RefCell<WebRTC>>, candidate: Candidate) {
+        let candidate_init = RtcIceCandidateInit::new(&candidate.candidate);
+        candidate_init.set_sdp_m_line_index(Some(candidate.sdp_m_line_index));
+        candidate_init.set_sdp_mid(Some(&candidate.sdp_mid));
+        let add_candidate = match web_rtc.as_ref().borrow().peer_connection() {
+            Some(connection) => connection.add_ice_candidate_with_opt_rtc_ice_candidate_init(Some(&candidate_init)),
+            None => return,
+        };
+        spawn_local(async move {
+            // Candidates of a previous ICE generation are refused, the connection goes on without them
+            if let Err(err) = JsFuture::from(add_candidate).await {
+                log::warn!("{}", CustomError::IceError(js_error_message(&err)));
             }
+        });
+    }
+
+    /// Fails when the data channel is not open, the message did not leave
+    pub fn send_webrtc_message(web_rtc: Rc<RefCell<WebRTC>>, message: &str) -> Result<(), CustomError> {
+        match &web_rtc.as_ref().borrow().data_channel {
+            Some(data_channel) if data_channel.ready_state() == RtcDataChannelState::Open => data_channel
+                .send_with_str(message)
+                .map_err(|err| CustomError::DataChannelError(js_error_message(&err))),
+            _ => Err(CustomError::DataChannelError(String::from("The data channel is not open"))),
+        }
+    }
+
+    /// Collects the stats of the peer connection, `callback` is called once the report is ready.
+    pub fn get_stats(web_rtc: Rc<RefCell<WebRTC>>, callback: Callback<ConnectionStats>) {
+        // Nothing to measure before the first connection
+        let stats_promise = match &web_rtc.as_ref().borrow().connection {
+            Some(connection) => connection.get_stats(),
+            None => return,
         };
+        spawn_local(async move {
+            match JsFuture::from(stats_promise).await {
+                Ok(report) => {
+                    // RTCStatsReport is a read-only Map of RTCStats dictionaries
+                    let mut raw_stats = vec![];
+                    report.unchecked_into::<Map>().for_each(&mut |stats, _| {
+                        let parsed = js_sys::JSON::stringify(&stats)
+                            .map_err(CustomError::from)
+                            .and_then(|json| Ok(serde_json::from_str::<RawStats>(&String::from(json))?));
+                        match parsed {
+                            Ok(parsed) => raw_stats.push(parsed),
+                            Err(err) => log::warn!("Ignoring unreadable stats {}", err),
+                        }
+                    });
+                    callback.emit(ConnectionStats::from_raw_stats(js_sys::Date::now(), &raw_stats));
+                }
+                Err(err) => log::error!("Could not get the connection stats: {}", js_error_message(&err)),
+            }
+        });
     }
 
     fn handle_socket_message(web_rtc: Rc<RefCell<WebRTC>>, socket_message: SocketMessage) {
         match socket_message {
             SocketMessage::JoinedRoom { content } => {
-                WebRTC::join_room(web_rtc, content);
+                WebRTC::dispatch(&web_rtc, Event::JoinedRoom(content.room));
             }
             SocketMessage::NewUser { .. } => {}
+            SocketMessage::LeaveRoom { .. } => {}
+            // Handled by the presence transport of the `PeerAgent`
+            SocketMessage::PresenceSubscribe { .. } | SocketMessage::PresenceNotify { .. } => {}
             SocketMessage::SignalMessageToClient {
                 content: SignalingMessage::UserHere { message },
             } => {
-                WebRTC::handle_user_here(web_rtc, message);
+                WebRTC::dispatch(&web_rtc, Event::PeerHere(message));
             }
             SocketMessage::SignalMessageToClient {
                 content: SignalingMessage::ICECandidate { message },
             } => {
-                WebRTC::handle_ice_candidate(web_rtc, message);
+                WebRTC::handle_ice_candidate(&web_rtc, message);
+            }
+            SocketMessage::SignalMessageToClient {
+                content: SignalingMessage::SDP { message, order },
+            } => {
+                WebRTC::handle_sdp_message(&web_rtc, message, order);
             }
             SocketMessage::SignalMessageToClient {
-                content: SignalingMessage::SDP { message },
+                content: SignalingMessage::UserLeft,
             } => {
-                WebRTC::handle_sdp_message(web_rtc, message);
+                WebRTC::dispatch(&web_rtc, Event::PeerLeft);
             }
             SocketMessage::SignalMessageFromClient { .. } => {}
         }
     }
 
-    fn join_room(web_rtc: Rc<RefCell<WebRTC>>, content: Room) {
-        (*web_rtc.as_ref().borrow_mut()).room = Some(content.room);
-    }
+    // Both peers create the same negotiated channel, there is no ondatachannel event to wait for
+    fn open_data_channel(web_rtc: &Rc<RefCell<WebRTC>>, label: &str, id: u16) {
+        let cloned_on_message = web_rtc.clone();
+        let on_message_data_channel_callback =
+            Closure::wrap(Box::new(move |ev: MessageEvent| {
+                let message = match ev.data().as_string() {
+                    Some(message) => message,
+                    None => return log::warn!("Received message error"),
+                };
+                let on_message = cloned_on_message.borrow().config.on_message.clone();
+                // Emitted without borrowing, the receiver may answer right away
+                match on_message {
+                    Some(on_message) => on_message.emit(message),
+                    None => cloned_on_message
+                        .borrow_mut()
+                        .publish(event_bus::Event::Chat(ChatMessage::new(SenderType::YOU, message))),
+                }
+            }) as BoxDynValue<MessageEvent>);
+
+        let cloned_on_open = web_rtc.clone();
+        let on_open_data_channel_callback: SingleArgClosure<JsValue> =
+            Closure::wrap(Box::new(move |_: JsValue| {
+                log::info!("Data channel opened");
+                cloned_on_open.borrow_mut().set_state(ConnectionState::Connected);
+                WebRTC::dispatch(&cloned_on_open, Event::ChannelOpen);
+            }));
+
+        let cloned_on_close = web_rtc.clone();
+        let on_close_data_channel_callback: SingleArgClosure<JsValue> =
+            Closure::wrap(Box::new(move |_: JsValue| {
+                log::info!("Data channel closed");
+                cloned_on_close.borrow_mut().set_state(ConnectionState::Closed);
+            }));
 
-    fn handle_user_here(web_rtc: Rc<RefCell<WebRTC>>, signaling_id: u16) {
         let mut borrow_mut = web_rtc.as_ref().borrow_mut();
-        if !borrow_mut.signaling_channel_opened {
-            let current_room = &borrow_mut.room;
-            let mut data_channel_init = RtcDataChannelInit::new();
-            data_channel_init.negotiated(true);
-            data_channel_init.id(signaling_id);
-            let data_channel = borrow_mut
-                .connection
-                .create_data_channel_with_data_channel_dict(
-                    &(current_room.as_ref().unwrap()),
-                    &data_channel_init,
-                );
+        let connection = match borrow_mut.peer_connection() {
+            Some(connection) => connection,
+            None => return,
+        };
+        let data_channel_init = RtcDataChannelInit::new();
+        data_channel_init.set_negotiated(true);
+        data_channel_init.set_id(id);
+        let data_channel = connection.create_data_channel_with_data_channel_dict(label, &data_channel_init);
+
+        let on_message = borrow_mut.callbacks.register(on_message_data_channel_callback);
+        let on_open = borrow_mut.callbacks.register(on_open_data_channel_callback);
+        let on_close = borrow_mut.callbacks.register(on_close_data_channel_callback);
+        data_channel.set_onmessage(Some(&on_message));
+        data_channel.set_onopen(Some(&on_open));
+        data_channel.set_onclose(Some(&on_close));
+        borrow_mut.set_state(ConnectionState::Connecting);
+        borrow_mut.data_channel = Some(data_channel);
+    }
 
-            let cloned_on_message = web_rtc.clone();
-            let on_message_data_channel_callback =
-                Closure::wrap(Box::new(move |ev: MessageEvent| {
-                    let mut on_message_borrowed = cloned_on_message.borrow_mut();
-                    if let Some(message) = ev.data().as_string() {
-                        on_message_borrowed.event_bus.send(Request::EventBusMsg(ChatMessage::new(SenderType::YOU, message)));
-                    } else {
-                        log::warn!("Received message error");
-                    }
-                }) as BoxDynValue<MessageEvent>);
-
-            data_channel.set_onmessage(Some(
-                on_message_data_channel_callback.as_ref().unchecked_ref(),
-            ));
-            on_message_data_channel_callback.forget();
-            borrow_mut.data_channel = Some(data_channel);
-        }
-    }
-
-    fn handle_ice_candidate(web_rtc: Rc<RefCell<WebRTC>>, candidate: Candidate) {
-        log::info!("ICE: Receive ice_candidate from signaling server");
-
-        let mut borrowed = web_rtc.as_ref().borrow_mut();
-        let remote_description: Option<RtcSessionDescription> =
-            borrowed.connection.remote_description();
-
-        if remote_description.is_none() {
-            let mut candidate_init = RtcIceCandidateInit::new(&candidate.candidate);
-            candidate_init.sdp_m_line_index(Some(candidate.sdp_m_line_index));
-            candidate_init.sdp_mid(Some(&candidate.sdp_mid));
-            borrowed.candidates_buffer.push(candidate_init);
-        } else {
-            let mut candidate_init = RtcIceCandidateInit::new(&candidate.candidate);
-            candidate_init.sdp_m_line_index(Some(candidate.sdp_m_line_index));
-            candidate_init.sdp_mid(Some(&candidate.sdp_mid));
-            let print_error_callback = Closure::wrap(Box::new(|err| {
-                log::error!("remote description {:?}", err)
-            }) as BoxDynValue<JsValue>);
-            let print_success_callback = Closure::wrap(Box::new(|_| {}) as BoxDynValue<JsValue>);
-
-            let _ = borrowed
-                .connection
-                .add_ice_candidate_with_opt_rtc_ice_candidate_init(Some(&candidate_init))
-                .then(&print_success_callback)
-                .catch(&print_error_callback);
-            print_error_callback.forget();
-            print_success_callback.forget();
-        }
-    }
-
-    fn handle_sdp_message(web_rtc: Rc<RefCell<WebRTC>>, sdp_message: SDPMessage) {
-        let description_init = RtcSessionDescriptionInit::try_from(sdp_message).unwrap();
-        let clone = web_rtc.clone();
-
-        let send_sdp_callback: SingleArgClosure<JsValue> = Closure::wrap(Box::new(move |_: JsValue| {
-            log::info!("Step 7: Handle SDP, send SDP answer");
-            let borrow_mut = clone.borrow_mut();
-            let message_to_send = SocketMessage::SignalMessageFromClient {
-                content: SignalingMessage::SDP {
-                    message: SDPMessage::try_from(
-                        borrow_mut.connection.local_description().unwrap(),
-                    ).unwrap(),
-                },
-            };
-            let message_to_send = serde_json::to_string(&message_to_send).unwrap();
-            match borrow_mut.socket.send_with_str(&message_to_send) {
-                Ok(_) => log::info!("binary message successfully sent"),
-                Err(err) => log::error!("error sending message: {:?}", err),
-            }
-        }));
-
-        let set_local_clone = web_rtc.clone();
-        let set_local_description_callback: SingleArgClosure<JsValue> = Closure::wrap(Box::new(move |descriptor: JsValue| {
-            let sdp_message = descriptor.into_serde::<SDPMessage>().unwrap();
-            let session_description_init =
-                RtcSessionDescriptionInit::try_from(sdp_message).unwrap();
-            let _ = set_local_clone
-                .borrow()
-                .connection
-                .set_local_description(&session_description_init)
-                .then(&send_sdp_callback);
-            log::info!("Step 6: Handle SDP, set_local_description");
-        }));
-
-        let clone_remote_description_success = web_rtc.clone();
-        let remote_description_success_callback: SingleArgClosure<JsValue> = Closure::wrap(Box::new(move |_: JsValue| {
-            if clone_remote_description_success
-                .as_ref()
-                .borrow()
-                .connection
-                .remote_description()
-                .unwrap()
-                .type_()
-                == RtcSdpType::Offer
-            {
-                log::info!("Step 5: Handle SDP, create_answer");
-                let _ = clone_remote_description_success
-                    .as_ref()
-                    .borrow()
-                    .connection
-                    .create_answer()
-                    .then(&set_local_description_callback);
-            }
-            // send Queued Candidates
-            for candidate in &clone_remote_description_success
-                .as_ref()
-                .borrow()
-                .candidates_buffer
-            {
-                let _ = clone_remote_description_success
-                    .as_ref()
-                    .borrow()
-                    .connection
-                    .add_ice_candidate_with_opt_rtc_ice_candidate_init(Some(&candidate));
+    fn handle_ice_candidate(web_rtc: &Rc<RefCell<WebRTC>>, candidate: Candidate) {
+        match candidate.candidate.parse::<IceCandidate>() {
+            Ok(parsed) => log::info!(
+                "ICE: Receive {} {} candidate from signaling server",
+                parsed.candidate_type,
+                parsed.transport
+            ),
+            Err(err) => log::warn!("ICE: Receive unreadable candidate: {}", err),
+        }
+        WebRTC::dispatch(web_rtc, Event::RemoteCandidate(candidate));
+    }
+
+    fn handle_sdp_message(web_rtc: &Rc<RefCell<WebRTC>>, sdp_message: SDPMessage, order: u32) {
+        match sdp_message.parse() {
+            Ok(Some(description)) => log::info!("Remote {:?}: {}", sdp_message.type_, description.summary()),
+            Ok(None) => log::info!("Remote {:?}", sdp_message.type_),
+            Err(err) => {
+                web_rtc.as_ref().borrow_mut().report(err);
+                return;
             }
-        }));
-
-        log::info!("Step 4: Handle SDP, set_remote_description");
-        let _ = web_rtc
-            .as_ref()
-            .borrow()
-            .connection
-            .set_remote_description(&description_init)
-            .then(&remote_description_success_callback);
-        remote_description_success_callback.forget();
+        }
+        WebRTC::dispatch(web_rtc, Event::RemoteDescription(sdp_message, order));
     }
 }
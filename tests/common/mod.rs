@@ -0,0 +1,36 @@
+//! Helpers shared by the browser tests
+#![allow(dead_code)]
+
+use js_sys::Promise;
+use wasm_bindgen_futures::JsFuture;
+
+use prust::utils::participants::Participants;
+
+pub async fn sleep(delay_ms: i32) {
+    let promise = Promise::new(&mut |resolve, _| {
+        web_sys::window()
+            .unwrap()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, delay_ms)
+            .unwrap();
+    });
+    JsFuture::from(promise).await.unwrap();
+}
+
+/// Polls `condition` for ten seconds at most
+pub async fn wait_until(mut condition: impl FnMut() -> bool) -> bool {
+    for _ in 0..100 {
+        if condition() {
+            return true;
+        }
+        sleep(100).await;
+    }
+    condition()
+}
+
+pub fn participants(user_from: &str, user_to: &str) -> Participants {
+    Participants {
+        user_from: user_from.to_string(),
+        user_to: user_to.to_string(),
+        room: None,
+    }
+}
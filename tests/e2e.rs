@@ -0,0 +1,189 @@
+//! Drives the whole application through the DOM, runs in a browser with `cargo make test`
+#![cfg(target_arch = "wasm32")]
+
+use std::{cell::RefCell, rc::Rc};
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen_test::*;
+use web_sys::{Element, HtmlElement, HtmlInputElement, HtmlTextAreaElement};
+use yew::Callback;
+
+use prust::signaling::SignalingConfig;
+use prust::utils::ice_candidate::CandidatePolicy;
+use prust::web_rtc::{WebRTC, WebRTCConfig};
+use prust::{App, AppProps};
+
+mod common;
+use common::{participants, wait_until};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn mount_app(signaling: SignalingConfig) -> Element {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let root = document.create_element("div").unwrap();
+    document.body().unwrap().append_child(&root).unwrap();
+    yew::App::<App>::new().mount_with_props(
+        root.clone(),
+        AppProps {
+            signaling: Some(signaling),
+        },
+    );
+    root
+}
+
+fn find<T: JsCast>(root: &Element, selector: &str) -> T {
+    root.query_selector(selector)
+        .unwrap()
+        .unwrap_or_else(|| panic!("Nothing matches {}", selector))
+        .dyn_into::<T>()
+        .unwrap()
+}
+
+fn exists(root: &Element, selector: &str) -> bool {
+    root.query_selector(selector).unwrap().is_some()
+}
+
+fn conversation(root: &Element) -> String {
+    find::<Element>(root, ".conversation-container").text_content().unwrap_or_default()
+}
+
+#[wasm_bindgen_test]
+async fn chats_with_a_peer_through_the_ui() {
+    let hub = SignalingConfig::InMemory(String::from("e2e"));
+    let root = mount_app(hub.clone());
+
+    // The other side is a peer without UI, it records what it receives
+    let received = Rc::new(RefCell::new(Vec::<String>::new()));
+    let received_clone = received.clone();
+    let friend = Rc::new(RefCell::new(WebRTC::new(WebRTCConfig {
+        signaling: hub,
+        on_message: Some(Callback::from(move |message| received_clone.borrow_mut().push(message))),
+        ..WebRTCConfig::default()
+    })));
+    WebRTC::connect(friend.clone(), participants("bob", "alice"), CandidatePolicy::All).unwrap();
+
+    find::<HtmlInputElement>(&root, "#name").set_value("alice");
+    find::<HtmlInputElement>(&root, "#recipient").set_value("bob");
+    find::<HtmlElement>(&root, "#connect").click();
+    assert!(wait_until(|| exists(&root, ".status--connected")).await);
+
+    find::<HtmlTextAreaElement>(&root, ".chatbox__form textarea").set_value("Hello bob");
+    find::<HtmlElement>(&root, ".chatbox__form input[type=button]").click();
+    assert!(wait_until(|| received.borrow().iter().any(|message| message == "Hello bob")).await);
+    assert!(conversation(&root).contains("Hello bob"));
+
+    WebRTC::send_webrtc_message(friend.clone(), "Hello alice").unwrap();
+    assert!(wait_until(|| conversation(&root).contains("Hello alice")).await);
+    assert!(exists(&root, ".message--u"));
+
+    find::<HtmlElement>(&root, "#disconnect").click();
+    WebRTC::disconnect(friend);
+}
+
+#[wasm_bindgen_test]
+async fn chats_with_the_demo_bot() {
+    let root = mount_app(SignalingConfig::InMemory(String::from("e2e-demo")));
+
+    let select = find::<web_sys::HtmlSelectElement>(&root, "#signaling");
+    select.set_value("demo");
+    select
+        .dispatch_event(&web_sys::Event::new("change").unwrap())
+        .unwrap();
+    assert!(wait_until(|| exists(&root, "#start-demo")).await);
+    find::<HtmlElement>(&root, "#start-demo").click();
+    assert!(wait_until(|| exists(&root, ".status--connected")).await);
+
+    find::<HtmlTextAreaElement>(&root, ".chatbox__form textarea").set_value("Echo?");
+    find::<HtmlElement>(&root, ".chatbox__form input[type=button]").click();
+    assert!(wait_until(|| exists(&root, ".message--u")).await);
+    assert!(find::<Element>(&root, ".message--u").text_content().unwrap_or_default().contains("Echo?"));
+}
+
+#[wasm_bindgen_test]
+async fn keeps_a_session_and_a_draft_per_conversation() {
+    let root = mount_app(SignalingConfig::InMemory(String::from("e2e-conversations")));
+
+    let select = find::<web_sys::HtmlSelectElement>(&root, "#signaling");
+    select.set_value("demo");
+    select
+        .dispatch_event(&web_sys::Event::new("change").unwrap())
+        .unwrap();
+    assert!(wait_until(|| exists(&root, "#start-demo")).await);
+    find::<HtmlElement>(&root, "#start-demo").click();
+    assert!(wait_until(|| exists(&root, ".app__status.status--connected")).await);
+    let textarea = find::<HtmlTextAreaElement>(&root, ".chatbox__form textarea");
+    textarea.set_value("Half written");
+
+    find::<HtmlElement>(&root, "#new-conversation").click();
+    assert!(wait_until(|| root.query_selector_all(".sidebar__item").unwrap().length() == 2).await);
+    assert!(!exists(&root, ".app__status.status--connected"));
+    assert_eq!(textarea.value(), "");
+
+    find::<HtmlElement>(&root, ".sidebar__item:first-child .sidebar__select").click();
+    assert!(wait_until(|| exists(&root, ".app__status.status--connected")).await);
+    assert_eq!(textarea.value(), "Half written");
+}
+
+#[wasm_bindgen_test]
+async fn saves_the_settings_and_refuses_an_incomplete_turn_server() {
+    let root = mount_app(SignalingConfig::InMemory(String::from("e2e-settings")));
+
+    find::<HtmlElement>(&root, "#open-settings").click();
+    assert!(wait_until(|| exists(&root, "#save-settings")).await);
+    find::<HtmlTextAreaElement>(&root, "#settings-ice-servers").set_value("turn:turn.example.org:3478");
+    find::<HtmlElement>(&root, "#save-settings").click();
+    assert!(wait_until(|| exists(&root, ".settings__error")).await);
+
+    find::<HtmlTextAreaElement>(&root, "#settings-ice-servers").set_value("stun:stun.example.org:3478");
+    find::<HtmlInputElement>(&root, "#settings-name").set_value("alice");
+    find::<HtmlElement>(&root, "#save-settings").click();
+    assert!(wait_until(|| !exists(&root, ".settings")).await);
+    assert_eq!(find::<HtmlInputElement>(&root, "#name").value(), "alice");
+
+    let storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
+    assert!(storage.get_item("prust-settings").unwrap().unwrap().contains("stun.example.org"));
+    storage.remove_item("prust-settings").unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn keeps_the_settings_of_a_newer_version_until_reset() {
+    let storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
+    storage.set_item("prust-settings", r#"{"version": 99, "display_name": "future"}"#).unwrap();
+    let root = mount_app(SignalingConfig::InMemory(String::from("e2e-newer-settings")));
+    assert!(wait_until(|| exists(&root, ".toast--error")).await);
+
+    find::<HtmlElement>(&root, "#toggle-theme").click();
+    assert!(storage.get_item("prust-settings").unwrap().unwrap().contains("future"));
+
+    find::<HtmlElement>(&root, "#open-settings").click();
+    assert!(wait_until(|| exists(&root, "#reset-settings")).await);
+    find::<HtmlElement>(&root, "#reset-settings").click();
+    assert!(wait_until(|| !storage.get_item("prust-settings").unwrap().unwrap().contains("future")).await);
+    storage.remove_item("prust-settings").unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn toggles_the_theme_and_applies_a_custom_one() {
+    let root = mount_app(SignalingConfig::InMemory(String::from("e2e-theme")));
+    let document_element = web_sys::window().unwrap().document().unwrap().document_element().unwrap();
+    let theme = || document_element.get_attribute("data-theme").unwrap_or_default();
+    let before = theme();
+
+    find::<HtmlElement>(&root, "#toggle-theme").click();
+    assert!(wait_until(|| theme() != before).await);
+    assert!(theme() == "light" || theme() == "dark");
+
+    find::<HtmlElement>(&root, "#open-settings").click();
+    assert!(wait_until(|| exists(&root, "#save-settings")).await);
+    find::<HtmlTextAreaElement>(&root, "#settings-custom-theme")
+        .set_value(r##"{"name": "Night", "base": "dark", "colors": {"color-accent": "#ff9800"}}"##);
+    find::<web_sys::HtmlSelectElement>(&root, "#settings-theme").set_value("custom");
+    find::<HtmlElement>(&root, "#save-settings").click();
+    assert!(wait_until(|| !exists(&root, ".settings")).await);
+    assert_eq!(theme(), "dark");
+    let style = document_element.dyn_into::<HtmlElement>().unwrap().style();
+    assert_eq!(style.get_property_value("--color-accent").unwrap(), "#ff9800");
+
+    let storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
+    storage.remove_item("prust-settings").unwrap();
+}
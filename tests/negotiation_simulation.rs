@@ -0,0 +1,390 @@
+//! Deterministic simulation of two peers negotiating, with a fake peer connection and signaling.
+//! Every seed replays a different interleaving of browser operations and signaling messages.
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::collections::VecDeque;
+
+use prust::negotiation::{Command, Event, Negotiation, SignalingState};
+use prust::utils::socket::{Candidate, SDPMessage, SdpType, SignalingMessage};
+
+const SEEDS: u64 = 2_000;
+const MAX_STEPS: usize = 10_000;
+const CANDIDATES_PER_DESCRIPTION: usize = 2;
+
+// xorshift64, good enough to shuffle and without dependency
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next() % 100 < percent
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Network {
+    // Candidates overtake descriptions and each other
+    reorder: bool,
+    // Descriptions overtake each other too
+    reorder_descriptions: bool,
+    duplicate: bool,
+    // The offerer restarts ICE once connected
+    restart_ice: bool,
+}
+
+// The description a message belongs to: "Offer alice 2" or "candidate alice 2 0"
+fn generation(text: &str) -> usize {
+    text.split_whitespace().nth(2).and_then(|number| number.parse().ok()).unwrap_or(0)
+}
+
+enum Task {
+    Event(Event),
+    CreateOffer,
+    CreateAnswer,
+    SetRemoteDescription(SDPMessage),
+}
+
+/// The parts of RTCPeerConnection the negotiation relies on, operations complete in order.
+/// Like the ufrag check of the browsers, a candidate is only added to the description it was gathered for.
+struct FakePeerConnection {
+    name: &'static str,
+    signaling_state: SignalingState,
+    local: Option<SDPMessage>,
+    stable_local: Option<SDPMessage>,
+    remote: Option<SDPMessage>,
+    channel_created: bool,
+    negotiation_needed: bool,
+    descriptions_created: usize,
+    // Generations of the added candidates, the others were refused
+    candidates_added: Vec<usize>,
+}
+
+struct Peer {
+    negotiation: Negotiation,
+    connection: FakePeerConnection,
+    tasks: VecDeque<Task>,
+    channel_open: bool,
+}
+
+impl Peer {
+    fn new(name: &'static str, other_name: &str) -> Self {
+        let mut tasks = VecDeque::new();
+        tasks.push_back(Task::Event(Event::JoinedRoom(String::from("alice:bob"))));
+        tasks.push_back(Task::Event(Event::PeerHere(0)));
+        Peer {
+            negotiation: Negotiation::new(Negotiation::is_polite(name, other_name)),
+            connection: FakePeerConnection {
+                name,
+                signaling_state: SignalingState::Stable,
+                local: None,
+                stable_local: None,
+                remote: None,
+                channel_created: false,
+                negotiation_needed: false,
+                descriptions_created: 0,
+                candidates_added: vec![],
+            },
+            tasks,
+            channel_open: false,
+        }
+    }
+
+    // Runs the next task, the messages to send go to `outbox`
+    fn step(&mut self, outbox: &mut Vec<SignalingMessage>, trace: &mut Vec<String>) {
+        let task = match self.tasks.pop_front() {
+            Some(task) => task,
+            None => return,
+        };
+        match task {
+            Task::Event(event) => {
+                trace.push(format!("{} handles {:?}", self.connection.name, event));
+                for command in self.negotiation.handle(event) {
+                    self.execute(command, outbox);
+                }
+            }
+            Task::CreateOffer => self.create_description(SdpType::Offer),
+            Task::CreateAnswer => self.create_description(SdpType::Answer),
+            Task::SetRemoteDescription(description) => self.set_remote_description(description),
+        }
+    }
+
+    fn execute(&mut self, command: Command, outbox: &mut Vec<SignalingMessage>) {
+        match command {
+            Command::OpenDataChannel { .. } => {
+                self.connection.channel_created = true;
+                if self.connection.remote.is_none() {
+                    self.connection.negotiation_needed = true;
+                    if self.connection.signaling_state == SignalingState::Stable {
+                        self.tasks.push_back(Task::Event(Event::NegotiationNeeded));
+                    }
+                }
+            }
+            Command::CreateOffer { .. } => self.tasks.push_back(Task::CreateOffer),
+            Command::CreateAnswer => self.tasks.push_back(Task::CreateAnswer),
+            Command::SetRemoteDescription(description) => {
+                self.tasks.push_back(Task::SetRemoteDescription(description))
+            }
+            Command::AddIceCandidate(candidate) => {
+                let remote = match &self.connection.remote {
+                    Some(remote) => generation(&remote.sdp),
+                    None => panic!("{} adds a candidate without remote description", self.connection.name),
+                };
+                if generation(&candidate.candidate) == remote {
+                    self.connection.candidates_added.push(remote);
+                }
+            }
+            Command::Send(message) => outbox.push(message),
+            Command::Close => panic!("{} closes the connection", self.connection.name),
+        }
+    }
+
+    fn create_description(&mut self, type_: SdpType) {
+        let allowed = match type_ {
+            SdpType::Offer => self.connection.signaling_state != SignalingState::HaveRemoteOffer,
+            _ => self.connection.signaling_state == SignalingState::HaveRemoteOffer,
+        };
+        if !allowed {
+            self.tasks.push_back(Task::Event(Event::LocalDescriptionFailed));
+            return;
+        }
+
+        self.connection.descriptions_created += 1;
+        let description = SDPMessage {
+            type_,
+            sdp: format!("{:?} {} {}", type_, self.connection.name, self.connection.descriptions_created),
+        };
+        self.connection.local = Some(description.clone());
+        if type_ == SdpType::Offer {
+            self.connection.signaling_state = SignalingState::HaveLocalOffer;
+        } else {
+            self.on_stable();
+        }
+        self.tasks.push_back(Task::Event(Event::LocalDescriptionSet(description)));
+        for index in 0..CANDIDATES_PER_DESCRIPTION {
+            self.tasks.push_back(Task::Event(Event::LocalCandidate(Candidate {
+                candidate: format!(
+                    "candidate {} {} {}",
+                    self.connection.name, self.connection.descriptions_created, index
+                ),
+                sdp_mid: String::from("0"),
+                sdp_m_line_index: 0,
+            })));
+        }
+    }
+
+    fn set_remote_description(&mut self, description: SDPMessage) {
+        let state = self.connection.signaling_state;
+        let allowed = match description.type_ {
+            // An offer received with a local offer rolls it back
+            SdpType::Offer => state != SignalingState::HaveRemoteOffer,
+            _ => state == SignalingState::HaveLocalOffer,
+        };
+        if !allowed {
+            self.tasks.push_back(Task::Event(Event::RemoteDescriptionFailed));
+            return;
+        }
+
+        let type_ = description.type_;
+        self.connection.remote = Some(description);
+        if type_ == SdpType::Offer {
+            self.connection.local = self.connection.stable_local.clone();
+            self.connection.signaling_state = SignalingState::HaveRemoteOffer;
+        } else {
+            self.on_stable();
+        }
+        self.tasks.push_back(Task::Event(Event::RemoteDescriptionSet));
+    }
+
+    fn on_stable(&mut self) {
+        self.connection.signaling_state = SignalingState::Stable;
+        self.connection.stable_local = self.connection.local.clone();
+        if self.connection.local.is_some() && self.connection.remote.is_some() {
+            self.connection.negotiation_needed = false;
+        } else if self.connection.negotiation_needed {
+            self.tasks.push_back(Task::Event(Event::NegotiationNeeded));
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+impl FakePeerConnection {
+    fn knows_remote_candidates(&self) -> bool {
+        self.remote
+            .as_ref()
+            .is_some_and(|remote| self.candidates_added.contains(&generation(&remote.sdp)))
+    }
+}
+
+// Both connections agree on the session and know a candidate of the other side, or one of them with `both_sides` unset:
+// the connectivity checks of that side let the other one learn its address
+fn transport_connected(alice: &FakePeerConnection, bob: &FakePeerConnection, both_sides: bool) -> bool {
+    let same = |local: &Option<SDPMessage>, remote: &Option<SDPMessage>| match (local, remote) {
+        (Some(local), Some(remote)) => local == remote,
+        _ => false,
+    };
+    [alice, bob]
+        .iter()
+        .all(|connection| connection.channel_created && connection.signaling_state == SignalingState::Stable)
+        && if both_sides {
+            alice.knows_remote_candidates() && bob.knows_remote_candidates()
+        } else {
+            alice.knows_remote_candidates() || bob.knows_remote_candidates()
+        }
+        && same(&alice.local, &bob.remote)
+        && same(&bob.local, &alice.remote)
+}
+
+fn deliver(inbox: &mut Vec<SignalingMessage>, peer: &mut Peer, rng: &mut Rng, network: Network) {
+    if inbox.is_empty() {
+        return;
+    }
+    let first_description = inbox
+        .iter()
+        .position(|message| matches!(message, SignalingMessage::SDP { .. }));
+    let index = if network.reorder { rng.below(inbox.len()) } else { 0 };
+    // A description never overtakes another one, unless the network reorders them too
+    let index = match (first_description, &inbox[index]) {
+        (Some(first), SignalingMessage::SDP { .. }) if !network.reorder_descriptions => first,
+        _ => index,
+    };
+    let message = if network.duplicate && rng.chance(10) {
+        inbox[index].clone()
+    } else {
+        inbox.remove(index)
+    };
+    let event = match message {
+        SignalingMessage::SDP { message, order } => Event::RemoteDescription(message, order),
+        SignalingMessage::ICECandidate { message } => Event::RemoteCandidate(message),
+        other => panic!("Unexpected message {:?}", other),
+    };
+    peer.tasks.push_back(Task::Event(event));
+}
+
+fn simulate(seed: u64, network: Network) {
+    let mut rng = Rng::new(seed);
+    let mut alice = Peer::new("alice", "bob");
+    let mut bob = Peer::new("bob", "alice");
+    let mut to_alice = vec![];
+    let mut to_bob = vec![];
+    let mut trace = vec![];
+    let mut restarted = false;
+
+    for _ in 0..MAX_STEPS {
+        let connected = alice.negotiation.is_connected() && bob.negotiation.is_connected();
+        let settled = alice.is_idle() && bob.is_idle() && to_alice.is_empty() && to_bob.is_empty();
+        if connected && !network.restart_ice {
+            return;
+        }
+        if connected && settled {
+            // The data channel stays open during the restart, the new session must work on one side at least
+            if restarted {
+                if transport_connected(&alice.connection, &bob.connection, false) {
+                    return;
+                }
+                break;
+            }
+            let offerer = if alice.negotiation.is_offerer() { &mut alice } else { &mut bob };
+            trace.push(format!("{} restarts ICE", offerer.connection.name));
+            offerer.tasks.push_back(Task::Event(Event::RestartIce));
+            restarted = true;
+        }
+        match rng.below(4) {
+            0 => alice.step(&mut to_bob, &mut trace),
+            1 => bob.step(&mut to_alice, &mut trace),
+            2 => deliver(&mut to_alice, &mut alice, &mut rng, network),
+            _ => deliver(&mut to_bob, &mut bob, &mut rng, network),
+        }
+
+        if !alice.channel_open && transport_connected(&alice.connection, &bob.connection, true) {
+            for peer in [&mut alice, &mut bob].iter_mut() {
+                peer.channel_open = true;
+                peer.tasks.push_back(Task::Event(Event::ChannelOpen));
+            }
+        }
+        if alice.is_idle() && bob.is_idle() && to_alice.is_empty() && to_bob.is_empty() && !alice.channel_open {
+            break;
+        }
+    }
+    panic!(
+        "Seed {} did not connect:\n{}\nalice: {:?} {:?}\nbob: {:?} {:?}",
+        seed,
+        trace.join("\n"),
+        alice.connection.local,
+        alice.connection.remote,
+        bob.connection.local,
+        bob.connection.remote
+    );
+}
+
+const NETWORK: Network = Network {
+    reorder: false,
+    reorder_descriptions: false,
+    duplicate: false,
+    restart_ice: false,
+};
+
+#[test]
+fn connects_with_an_ordered_signaling() {
+    for seed in 0..SEEDS {
+        simulate(seed, NETWORK);
+    }
+}
+
+#[test]
+fn connects_with_reordered_candidates() {
+    for seed in 0..SEEDS {
+        simulate(seed, Network { reorder: true, ..NETWORK });
+    }
+}
+
+#[test]
+fn connects_with_duplicated_messages() {
+    for seed in 0..SEEDS {
+        simulate(seed, Network { duplicate: true, ..NETWORK });
+    }
+}
+
+#[test]
+fn connects_with_reordered_and_duplicated_messages() {
+    for seed in 0..SEEDS {
+        simulate(seed, Network { reorder: true, duplicate: true, ..NETWORK });
+    }
+}
+
+#[test]
+fn connects_with_reordered_descriptions() {
+    for seed in 0..SEEDS {
+        simulate(seed, Network { reorder: true, reorder_descriptions: true, duplicate: true, ..NETWORK });
+    }
+}
+
+#[test]
+fn restarts_ice_with_reordered_messages() {
+    for seed in 0..SEEDS {
+        simulate(seed, Network { reorder: true, restart_ice: true, ..NETWORK });
+    }
+}
+
+#[test]
+fn restarts_ice_with_reordered_descriptions() {
+    for seed in 0..SEEDS {
+        simulate(seed, Network { reorder: true, reorder_descriptions: true, duplicate: true, restart_ice: true });
+    }
+}
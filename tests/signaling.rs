@@ -0,0 +1,153 @@
+//! Runs in a browser with `cargo make test`
+#![cfg(target_arch = "wasm32")]
+
+use std::{cell::RefCell, rc::Rc};
+
+use wasm_bindgen_test::*;
+use yew::Callback;
+
+use prust::signaling::in_memory::InMemoryTransport;
+use prust::signaling::{SignalingConfig, SignalingTransport, TransportState};
+use prust::utils::callbacks::live_closures;
+use prust::utils::connection_state::ConnectionState;
+use prust::utils::ice_candidate::CandidatePolicy;
+use prust::utils::socket::{Presence, PresenceSubscription, SignalingMessage, SocketMessage};
+use prust::web_rtc::{WebRTC, WebRTCConfig};
+
+mod common;
+use common::{participants, sleep, wait_until};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn record(transport: &InMemoryTransport) -> Rc<RefCell<Vec<SocketMessage>>> {
+    let received = Rc::new(RefCell::new(vec![]));
+    let received_clone = received.clone();
+    transport.subscribe(Some(Callback::from(move |message| {
+        received_clone.borrow_mut().push(message)
+    })));
+    received
+}
+
+#[wasm_bindgen_test]
+async fn transports_of_a_hub_find_each_other() {
+    let alice = InMemoryTransport::new("find-each-other");
+    let bob = InMemoryTransport::new("find-each-other");
+    let alice_received = record(&alice);
+    let bob_received = record(&bob);
+
+    alice.send(&SocketMessage::NewUser { content: participants("alice", "bob") });
+    bob.send(&SocketMessage::NewUser { content: participants("bob", "alice") });
+
+    let user_here = |received: &Rc<RefCell<Vec<SocketMessage>>>| {
+        received.borrow().iter().any(|message| {
+            matches!(message, SocketMessage::SignalMessageToClient { content: SignalingMessage::UserHere { .. } })
+        })
+    };
+    assert!(wait_until(|| user_here(&alice_received) && user_here(&bob_received)).await);
+
+    bob.send(&SocketMessage::SignalMessageFromClient { content: SignalingMessage::UserLeft });
+    assert!(
+        wait_until(|| alice_received.borrow().iter().any(|message| {
+            matches!(message, SocketMessage::SignalMessageToClient { content: SignalingMessage::UserLeft })
+        }))
+        .await
+    );
+}
+
+#[wasm_bindgen_test]
+async fn transports_of_other_hubs_do_not_hear_each_other() {
+    let alice = InMemoryTransport::new("hub-a");
+    let bob = InMemoryTransport::new("hub-b");
+    let bob_received = record(&bob);
+
+    alice.send(&SocketMessage::NewUser { content: participants("alice", "bob") });
+    bob.send(&SocketMessage::NewUser { content: participants("bob", "alice") });
+    sleep(200).await;
+
+    // Only the JoinedRoom message bob got for himself
+    assert_eq!(bob_received.borrow().len(), 1);
+}
+
+fn subscribe_presence(transport: &InMemoryTransport, user: &str, contact: &str) {
+    transport.send(&SocketMessage::PresenceSubscribe {
+        content: PresenceSubscription {
+            user: user.to_string(),
+            contacts: vec![contact.to_string()],
+        },
+    });
+}
+
+fn saw(received: &Rc<RefCell<Vec<SocketMessage>>>, user: &str, online: bool) -> bool {
+    let expected = Presence {
+        user: user.to_string(),
+        online,
+    };
+    received.borrow().iter().any(|message| match message {
+        SocketMessage::PresenceNotify { content } => *content == expected,
+        _ => false,
+    })
+}
+
+#[wasm_bindgen_test]
+async fn contacts_see_each_other_come_and_go() {
+    let alice = InMemoryTransport::new("presence");
+    let alice_received = record(&alice);
+    subscribe_presence(&alice, "alice", "bob");
+    sleep(100).await;
+
+    let bob = InMemoryTransport::new("presence");
+    let bob_received = record(&bob);
+    subscribe_presence(&bob, "bob", "alice");
+    assert!(wait_until(|| saw(&alice_received, "bob", true) && saw(&bob_received, "alice", true)).await);
+
+    drop(bob);
+    assert!(wait_until(|| saw(&alice_received, "bob", false)).await);
+}
+
+#[wasm_bindgen_test]
+async fn reports_a_closed_transport() {
+    let transport = InMemoryTransport::new("closed");
+    let states = Rc::new(RefCell::new(vec![]));
+    let states_clone = states.clone();
+    transport.subscribe_state(Some(Callback::from(move |state| states_clone.borrow_mut().push(state))));
+
+    assert_eq!(transport.state(), TransportState::Open);
+    transport.close();
+    assert_eq!(transport.state(), TransportState::Closed);
+    assert_eq!(*states.borrow(), vec![TransportState::Closed]);
+}
+
+#[wasm_bindgen_test]
+async fn two_peers_negotiate_without_network() {
+    let config = || WebRTCConfig {
+        signaling: SignalingConfig::InMemory(String::from("negotiation")),
+        ..WebRTCConfig::default()
+    };
+    let live_before = live_closures();
+    let alice = Rc::new(RefCell::new(WebRTC::new(config())));
+    let bob = Rc::new(RefCell::new(WebRTC::new(config())));
+
+    WebRTC::connect(alice.clone(), participants("alice", "bob"), CandidatePolicy::All).unwrap();
+    WebRTC::connect(bob.clone(), participants("bob", "alice"), CandidatePolicy::All).unwrap();
+    assert_eq!(alice.borrow().state(), ConnectionState::Signaling);
+
+    // Both peers were told the other one is here and opened the data channel
+    assert!(
+        wait_until(|| alice.borrow().state() != ConnectionState::Signaling
+            && bob.borrow().state() != ConnectionState::Signaling)
+        .await
+    );
+    // Both offers collide, the polite peer gives its own up and the data channel opens
+    assert!(
+        wait_until(|| alice.borrow().state() == ConnectionState::Connected
+            && bob.borrow().state() == ConnectionState::Connected)
+        .await
+    );
+
+    assert!(live_closures() > live_before);
+
+    WebRTC::disconnect(alice.clone());
+    WebRTC::disconnect(bob.clone());
+    // Every closure of both connections was freed
+    assert_eq!(live_closures(), live_before);
+}